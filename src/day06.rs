@@ -1,4 +1,4 @@
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::{self, BufRead};
 use std::collections::{HashMap};
 
@@ -11,24 +11,29 @@ fn iterate_days(fishes_map: &HashMap<i32,i64>, days: i32) -> HashMap<i32,i64> {
       // example:
       // if new fish days is -1, created fish with counter of 8 = 9 + updated_fish
       *ret.entry(9 + updated_fish).or_insert(0) += fish_count;
-      updated_fish += days;
+      // the parent restarts its 7 days cycle
+      updated_fish += 7;
     }
     *ret.entry(updated_fish).or_insert(0) += fish_count;
   }
   return ret;
 }
 
-fn solve (data: &Vec<String>, days: i32) -> i64 {
-  let fishes: Vec<i32> = data.get(0)
+fn parse_fishes(data: &Vec<String>) -> Vec<usize> {
+  data.get(0)
     .unwrap()
     .split(",")
-    .map(|n|n.parse().unwrap())
-    .collect();
+    .map(|n|n.trim().parse().unwrap())
+    .collect()
+}
+
+fn solve (data: &Vec<String>, days: i32) -> i64 {
+  let fishes = parse_fishes(data);
 
   let mut fishes_map: HashMap<i32,i64> = HashMap::new();
 
   for f in fishes {
-    *fishes_map.entry(f).or_insert(0) += 1;
+    *fishes_map.entry(f as i32).or_insert(0) += 1;
   }
 
   let weeks = days / 7;
@@ -45,20 +50,195 @@ fn solve (data: &Vec<String>, days: i32) -> i64 {
   return fishes_map.values().sum();
 }
 
+// Fishes count by timer for every day from 0 to days, iterating one day at a time
+fn timeline(data: &Vec<String>, days: i32) -> Vec<HashMap<i32,i64>> {
+  let mut fishes_map: HashMap<i32,i64> = HashMap::new();
+  for f in parse_fishes(data) {
+    *fishes_map.entry(f as i32).or_insert(0) += 1;
+  }
+
+  let mut timeline = vec![fishes_map];
+  for _ in 0 .. days {
+    let next = iterate_days(timeline.last().unwrap(), 1);
+    timeline.push(next);
+  }
+
+  // day by day and weekly batches must agree
+  let last_total: i64 = timeline.last().unwrap().values().sum();
+  assert_eq!(last_total, solve(data, days), "Day by day population differs from batched one");
+  return timeline;
+}
+
+fn timeline_to_csv(timeline: &Vec<HashMap<i32,i64>>) -> String {
+  let timers: Vec<String> = (0..9).map(|t|format!("timer_{}", t)).collect();
+  let mut csv = format!("day,{},total\n", timers.join(","));
+  for (day, fishes_map) in timeline.iter().enumerate() {
+    let counts: Vec<String> = (0..9).map(|t|fishes_map.get(&t).unwrap_or(&0).to_string()).collect();
+    csv += &format!("{},{},{}\n", day, counts.join(","), fishes_map.values().sum::<i64>());
+  }
+  return csv;
+}
+
+// One bar per day, scaled on the largest population
+fn sparkline(timeline: &Vec<HashMap<i32,i64>>) -> String {
+  let bars = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+  let totals: Vec<i64> = timeline.iter().map(|fishes_map|fishes_map.values().sum()).collect();
+  let max = *totals.iter().max().unwrap();
+  totals
+    .iter()
+    .map(|total|bars[((*total as f64 / max as f64) * (bars.len() - 1) as f64).round() as usize])
+    .collect()
+}
 
-fn solution_1(data: &Vec<String>) -> i64 {
-  solve(data, 80)
+// Square matrix of fish counts. Operations return None on overflow, or work modulo
+// a number small enough for products to fit in a u128
+#[derive(Debug, Clone, PartialEq)]
+struct Matrix {
+  cells: Vec<Vec<u128>>,
 }
 
-fn solution_2(data: &Vec<String>) -> i64 {
-  solve(data, 256)
+impl Matrix {
+  fn identity(size: usize) -> Self {
+    let mut cells = vec![vec![0; size]; size];
+    for (i, row) in cells.iter_mut().enumerate() {
+      row[i] = 1;
+    }
+    Self { cells }
+  }
+
+  fn multiply(&self, other: &Matrix, modulus: Option<u64>) -> Option<Matrix> {
+    let size = self.cells.len();
+    let mut cells = vec![vec![0; size]; size];
+    for i in 0..size {
+      for j in 0..size {
+        let mut cell: u128 = 0;
+        for k in 0..size {
+          let product = self.cells[i][k].checked_mul(other.cells[k][j])?;
+          cell = match modulus {
+            Some(m) => (cell + product % m as u128) % m as u128,
+            None => cell.checked_add(product)?,
+          };
+        }
+        cells[i][j] = cell;
+      }
+    }
+    Some(Matrix { cells })
+  }
+
+  // Exponentiation by squaring, log2(exponent) multiplications
+  fn power(&self, exponent: u64, modulus: Option<u64>) -> Option<Matrix> {
+    let mut result = Matrix::identity(self.cells.len());
+    let mut base = self.clone();
+    let mut exponent = exponent;
+    while exponent > 0 {
+      if exponent & 1 == 1 {
+        result = result.multiply(&base, modulus)?;
+      }
+      exponent >>= 1;
+      if exponent > 0 {
+        base = base.multiply(&base, modulus)?;
+      }
+    }
+    Some(result)
+  }
+}
+
+#[derive(Debug, Clone)]
+struct LanternfishModel {
+  // days between two births of the same fish
+  spawn_cycle: usize,
+  // extra days before a newborn fish starts its first cycle
+  newborn_delay: usize,
+}
+
+impl LanternfishModel {
+  fn timers(&self) -> usize {
+    self.spawn_cycle + self.newborn_delay
+  }
+
+  // counts[t] fishes with timer t become, the next day, transition * counts
+  fn transition_matrix(&self) -> Matrix {
+    let size = self.timers();
+    let mut cells = vec![vec![0; size]; size];
+    for timer in 1..size {
+      cells[timer - 1][timer] = 1;
+    }
+    // fishes at 0 restart their cycle and give birth
+    cells[self.spawn_cycle - 1][0] += 1;
+    cells[size - 1][0] += 1;
+    Matrix { cells }
+  }
+
+  fn count_timers(&self, fishes: &Vec<usize>) -> Result<Vec<u128>, String> {
+    let mut counts: Vec<u128> = vec![0; self.timers()];
+    for fish in fishes {
+      match counts.get_mut(*fish) {
+        Some(count) => *count += 1,
+        None => return Err(format!("Timer {} is out of the model timers 0 to {}", fish, self.timers() - 1)),
+      }
+    }
+    return Ok(counts);
+  }
+
+  // Fishes after days, fails on invalid parameters or if it overflows a u128 without modulus
+  fn population(&self, fishes: &Vec<usize>, days: u64, modulus: Option<u64>) -> Result<u128, String> {
+    if self.spawn_cycle == 0 {
+      return Err("Spawn cycle must be at least 1 day".to_string());
+    }
+    if modulus == Some(0) {
+      return Err("Modulus must be at least 1".to_string());
+    }
+    let counts = self.count_timers(fishes)?;
+    let overflow = || format!("Too many fishes after {} days to count without a modulus", days);
+    let transition = self.transition_matrix().power(days, modulus).ok_or_else(overflow)?;
+    let mut total: u128 = 0;
+    for row in &transition.cells {
+      for (cell, count) in row.iter().zip(counts.iter()) {
+        let fishes = cell.checked_mul(*count).ok_or_else(overflow)?;
+        total = match modulus {
+          Some(m) => (total + fishes % m as u128) % m as u128,
+          None => total.checked_add(fishes).ok_or_else(overflow)?,
+        };
+      }
+    }
+    Ok(total)
+  }
+}
+
+const DEFAULT_MODEL: LanternfishModel = LanternfishModel { spawn_cycle: 7, newborn_delay: 2 };
+
+fn solution_1(data: &Vec<String>) -> u128 {
+  let result = DEFAULT_MODEL.population(&parse_fishes(data), 80, None).unwrap();
+  debug_assert_eq!(result, solve(data, 80) as u128);
+  result
+}
+
+fn solution_2(data: &Vec<String>) -> u128 {
+  let result = DEFAULT_MODEL.population(&parse_fishes(data), 256, None).unwrap();
+  debug_assert_eq!(result, solve(data, 256) as u128);
+  result
+}
+
+fn print_population(data: &Vec<String>, model: &LanternfishModel, days: u64, modulus: Option<u64>) {
+  match (model.population(&parse_fishes(data), days, modulus), modulus) {
+    (Ok(population), Some(m)) => println!("After {} days there are {} fishes modulo {}", days, population, m),
+    (Ok(population), None) => println!("After {} days there are {} fishes", days, population),
+    (Err(e), _) => println!("❌ {}", e),
+  }
 }
 
 fn main() {
   let test = false;
+  // population of a custom model, e.g. days: 1_000_000_000_000 with modulus: Some(1_000_000_007)
+  let custom_model: Option<LanternfishModel> = None;
+  let days: u64 = 1_000_000_000_000;
+  let modulus: Option<u64> = Some(1_000_000_007);
+  // export population by timer for every day up to this one
+  let timeline_days: Option<i32> = None;
+  let data: Vec<String>;
   if test {
     let file = File::open("inputs/day06.test.txt").unwrap();
-    let data: Vec<String> = io::BufReader::new(file)
+    data = io::BufReader::new(file)
                 .lines()
                 .map(|line|line.unwrap())
                 .collect();
@@ -69,7 +249,7 @@ fn main() {
   } else {
 
     let file = File::open("inputs/day06.txt").unwrap();
-    let data: Vec<String> = io::BufReader::new(file)
+    data = io::BufReader::new(file)
                 .lines()
                 .map(|line|line.unwrap())
                 .collect();
@@ -78,4 +258,15 @@ fn main() {
 
     println!(r#"🎉 Part 2 result is {:?}"#, solution_2(&data));
   }
+
+  if let Some(days) = timeline_days {
+    let timeline = timeline(&data, days);
+    fs::create_dir_all("outputs").unwrap();
+    fs::write("outputs/day06.timeline.csv", timeline_to_csv(&timeline)).unwrap();
+    println!("{}", sparkline(&timeline));
+  }
+
+  if let Some(model) = custom_model {
+    print_population(&data, &model, days, modulus);
+  }
 }
\ No newline at end of file
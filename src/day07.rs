@@ -1,95 +1,252 @@
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::{self, BufRead};
 
-fn evaluate_cost_constant(crabs: &Vec<i32>, pos: &i32) -> i32 {
-  let mut cost = 0;
+fn parse_crabs(data: &Vec<String>) -> Vec<i64> {
+  let mut crabs: Vec<i64> = data.get(0)
+    .unwrap()
+    .split(",")
+    .map(|n|n.trim().parse().unwrap())
+    .collect();
+
+  crabs.sort();
+  return crabs;
+}
 
-  for crab in crabs {
-    cost += (crab - pos).abs();
+// Fuel burnt by one crab moving `distance` steps
+trait CostFunction {
+  fn fuel(&self, distance: i64) -> i64;
+
+  // convex and non decreasing in the distance, so is the total cost in the position
+  fn is_convex(&self) -> bool;
+}
+
+// One fuel per step
+struct Constant;
+
+impl CostFunction for Constant {
+  fn fuel(&self, distance: i64) -> i64 {
+    distance
   }
 
-  return cost;
+  fn is_convex(&self) -> bool {
+    true
+  }
 }
 
+// Each step costs one more than the previous one: 1 + 2 + ... + distance
+struct Triangular;
 
-fn solution_1(data: &Vec<String>) -> i32 {
-  let mut crabs: Vec<i32> = data.get(0)
-    .unwrap()
-    .split(",")
-    .map(|n|n.parse().unwrap())
-    .collect();
+impl CostFunction for Triangular {
+  fn fuel(&self, distance: i64) -> i64 {
+    distance * (distance + 1) / 2
+  }
 
-  crabs.sort();
+  fn is_convex(&self) -> bool {
+    true
+  }
+}
 
-  let min_crab = crabs.get(0).unwrap();
-  let max_crab = crabs.get(crabs.len() -1).unwrap();
-  let mut min_cost = i32::MAX;
+struct Quadratic;
 
-  for pos in *min_crab .. *max_crab {
-    let cost = evaluate_cost_constant(&crabs, &pos);
-    if cost < min_cost {
-      min_cost = cost;
-    }
+impl CostFunction for Quadratic {
+  fn fuel(&self, distance: i64) -> i64 {
+    distance * distance
+  }
+
+  fn is_convex(&self) -> bool {
+    true
   }
-  return min_cost;
 }
 
+// User supplied fuel, convexity can't be checked so it has to be declared
+struct Custom<F: Fn(i64) -> i64> {
+  fuel: F,
+  convex: bool,
+}
 
-fn evaluate_cost_increasing(crabs: &Vec<i32>, pos: &i32) -> i32 {
-  let mut cost = 0;
+impl<F: Fn(i64) -> i64> CostFunction for Custom<F> {
+  fn fuel(&self, distance: i64) -> i64 {
+    (self.fuel)(distance)
+  }
 
-  for crab in crabs {
-    let distance = (crab - pos).abs();  
-    cost += (1 .. distance + 1).fold(0, |a,b|a+b);
+  fn is_convex(&self) -> bool {
+    self.convex
   }
+}
 
-  return cost;
+fn total_cost(crabs: &Vec<i64>, pos: i64, cost: &dyn CostFunction) -> i64 {
+  crabs.iter().map(|crab|cost.fuel((crab - pos).abs())).sum()
 }
 
-fn solution_2(data: &Vec<String>) -> i32 {
-  let mut crabs: Vec<i32> = data.get(0)
-    .unwrap()
-    .split(",")
-    .map(|n|n.parse().unwrap())
-    .collect();
+#[derive(Debug, Clone)]
+struct Alignment {
+  position: i64,
+  cost: i64,
+  // cost of every position between the first and the last crab
+  curve: Option<Vec<(i64, i64)>>,
+}
 
-  crabs.sort();
+// Tries every position between the first and the last crab, crabs must be sorted
+fn scan_min_cost(crabs: &Vec<i64>, cost: &dyn CostFunction) -> (i64, i64) {
+  let min_crab = *crabs.first().unwrap();
+  let max_crab = *crabs.last().unwrap();
 
-  let min_crab = crabs.get(0).unwrap();
-  let max_crab = crabs.get(crabs.len() -1).unwrap();
-  let mut min_cost = i32::MAX;
+  (min_crab ..= max_crab)
+    .map(|pos|(pos, total_cost(crabs, pos, cost)))
+    .min_by_key(|(_, cost)|*cost)
+    .unwrap()
+}
 
-  for pos in *min_crab .. *max_crab {
-    let cost = evaluate_cost_increasing(&crabs, &pos);
-    if cost < min_cost {
-      min_cost = cost;
+// Only valid for convex costs, crabs must be sorted
+fn ternary_search_min_cost(crabs: &Vec<i64>, cost: &dyn CostFunction) -> (i64, i64) {
+  let mut low = *crabs.first().unwrap();
+  let mut high = *crabs.last().unwrap();
+
+  while high - low > 2 {
+    let third = (high - low) / 3;
+    let (m1, m2) = (low + third, high - third);
+    let (cost_1, cost_2) = (total_cost(crabs, m1, cost), total_cost(crabs, m2, cost));
+    if cost_1 < cost_2 {
+      high = m2 - 1;
+    } else if cost_1 > cost_2 {
+      low = m1 + 1;
+    } else {
+      // a minimum lies between two equal costs
+      low = m1;
+      high = m2;
     }
   }
+
+  (low ..= high)
+    .map(|pos|(pos, total_cost(crabs, pos, cost)))
+    .min_by_key(|(_, cost)|*cost)
+    .unwrap()
+}
+
+fn optimize(crabs: &Vec<i64>, cost: &dyn CostFunction, with_curve: bool) -> Alignment {
+  let (position, min_cost) = if cost.is_convex() {
+    ternary_search_min_cost(crabs, cost)
+  } else {
+    scan_min_cost(crabs, cost)
+  };
+
+  let curve = if with_curve {
+    Some(
+      (*crabs.first().unwrap() ..= *crabs.last().unwrap())
+        .map(|pos|(pos, total_cost(crabs, pos, cost)))
+        .collect()
+    )
+  } else {
+    None
+  };
+
+  Alignment {
+    position,
+    cost: min_cost,
+    curve,
+  }
+}
+
+// The median minimizes the sum of distances, crabs must be sorted
+fn median_alignment(crabs: &Vec<i64>) -> (i64, i64) {
+  let median = crabs[crabs.len() / 2];
+  (median, total_cost(crabs, median, &Constant))
+}
+
+// The cost decreases up to the floor of the mean and increases from its ceiling,
+// so the best position is one of them
+fn mean_alignment(crabs: &Vec<i64>) -> (i64, i64) {
+  let sum: i64 = crabs.iter().sum();
+  let len = crabs.len() as i64;
+  let floor = sum.div_euclid(len);
+  let ceil = if sum.rem_euclid(len) == 0 { floor } else { floor + 1 };
+
+  (floor ..= ceil)
+    .map(|pos|(pos, total_cost(crabs, pos, &Triangular)))
+    .min_by_key(|(_, cost)|*cost)
+    .unwrap()
+}
+
+fn solution_1(data: &Vec<String>, verify: bool) -> i64 {
+  let crabs = parse_crabs(data);
+  let (_, min_cost) = median_alignment(&crabs);
+  if verify {
+    assert_eq!(min_cost, scan_min_cost(&crabs, &Constant).1, "Median alignment is not optimal");
+    assert_eq!(min_cost, optimize(&crabs, &Constant, false).cost, "Ternary search is not optimal");
+  }
+  return min_cost;
+}
+
+fn solution_2(data: &Vec<String>, verify: bool) -> i64 {
+  let crabs = parse_crabs(data);
+  let (_, min_cost) = mean_alignment(&crabs);
+  if verify {
+    assert_eq!(min_cost, scan_min_cost(&crabs, &Triangular).1, "Mean alignment is not optimal");
+    assert_eq!(min_cost, optimize(&crabs, &Triangular, false).cost, "Ternary search is not optimal");
+  }
   return min_cost;
 }
 
+// Best alignment for each cost function, and the cost curves as CSV
+fn compare_costs(data: &Vec<String>) -> String {
+  let crabs = parse_crabs(data);
+  let costs: Vec<(&str, Box<dyn CostFunction>)> = vec![
+    ("constant", Box::new(Constant)),
+    ("triangular", Box::new(Triangular)),
+    ("quadratic", Box::new(Quadratic)),
+    // moving at all costs 100 fuel on top of the distance
+    ("fixed_fee", Box::new(Custom { fuel: |d: i64|if d > 0 { 100 + d } else { 0 }, convex: false })),
+  ];
+
+  let mut curves: Vec<Vec<(i64, i64)>> = Vec::new();
+  for (name, cost) in &costs {
+    let alignment = optimize(&crabs, cost.as_ref(), true);
+    println!("{:>10}: position {} costs {}", name, alignment.position, alignment.cost);
+    curves.push(alignment.curve.unwrap());
+  }
+
+  let names: Vec<&str> = costs.iter().map(|(name, _)|*name).collect();
+  let mut csv = format!("position,{}\n", names.join(","));
+  for i in 0..curves[0].len() {
+    let row: Vec<String> = curves.iter().map(|curve|curve[i].1.to_string()).collect();
+    csv += &format!("{},{}\n", curves[0][i].0, row.join(","));
+  }
+  return csv;
+}
+
 fn main() {
   let test = false;
+  // check the closed form results against a scan of every position
+  let verify = false;
+  // print the best position for several cost functions and export their curves
+  let export_curves = false;
+  let data: Vec<String>;
   if test {
     let file = File::open("inputs/day07.test.txt").unwrap();
-    let data: Vec<String> = io::BufReader::new(file)
+    data = io::BufReader::new(file)
                 .lines()
                 .map(|line|line.unwrap())
                 .collect();
 
-    println!(r#"🧪 Part 1 test result is {:?}"#, solution_1(&data));
+    println!(r#"🧪 Part 1 test result is {:?}"#, solution_1(&data, verify));
 
-    println!(r#"🧪 Part 2 test result is {:?}"#, solution_2(&data));
+    println!(r#"🧪 Part 2 test result is {:?}"#, solution_2(&data, verify));
   } else {
 
     let file = File::open("inputs/day07.txt").unwrap();
-    let data: Vec<String> = io::BufReader::new(file)
+    data = io::BufReader::new(file)
                 .lines()
                 .map(|line|line.unwrap())
                 .collect();
 
-    println!(r#"🎉 Part 1 result is {:?}"#, solution_1(&data));
+    println!(r#"🎉 Part 1 result is {:?}"#, solution_1(&data, verify));
 
-    println!(r#"🎉 Part 2 result is {:?}"#, solution_2(&data));
+    println!(r#"🎉 Part 2 result is {:?}"#, solution_2(&data, verify));
   }
-}
\ No newline at end of file
+
+  if export_curves {
+    let csv = compare_costs(&data);
+    fs::create_dir_all("outputs").unwrap();
+    fs::write("outputs/day07.curves.csv", csv).unwrap();
+  }
+}
@@ -1,6 +1,6 @@
-use core::num;
 use std::collections::{HashSet, HashMap};
-use std::fs::File;
+use std::fmt;
+use std::fs::{self, File};
 use std::io::{self, BufRead};
 
 /*
@@ -44,212 +44,293 @@ Each digit has some segment on:
 8: 0,1,2,3,4,5,6
 9: 0,1,2,3,5,6
 
-Given all digits =>
-We can find 1,4,7,8 as they have an unique segments.len()
-
-digits[1] = digits.find(digit => digit.len() == 2)
-digits[4] = digits.find(digit => digit.len() = 4)
-digits[7] = digits.find(digit => digit.len() = 3)
-digits[8] = digits.find(digit => digit.len() = 7)
-
-Then we can find:
-segment 0 = digits[7].segments - digits[1].segments
-
-We have:
-1: 2,5
-4: 1,2,3,5
-7: 0,2,5
-8: 0,1,2,3,4,5,6
+Each line wires the segments in a different order, each wire can only light the
+segments allowed by the digits having as many segments as the patterns it belongs to.
+Propagating these constraints leaves few candidates per wire, the remaining wire
+permutations are checked against all patterns: a line is solved when exactly one matches
+*/
 
-We need to find:
-2: 0,2,3,4,6
-3: 0,2,3,5,6
-5: 0,1,3,5,6
+const WIRES: &str = "abcdefg";
 
-0: 0,1,2,4,5,6
-6: 0,1,3,4,5,6
-9: 0,1,2,3,5,6
+// canonical segments of each digit
+const SEGMENTS_BY_DIGIT: [&str; 10] = [
+  "abcefg", "cf", "acdeg", "acdfg", "bcdf", "abdfg", "abdefg", "acf", "abcdefg", "abcdfg",
+];
 
-digit 6 has 6 segments and includes only one of the two segments from digits[1]
-digits[6] = digits.find(digit => (digits.segments.len() == 6 && digits[1] - digit.segments).len() == 1 )
-This also get us both segments 2 and 5, among with segment 0
+fn sorted(pattern: &str) -> String {
+  let mut chars: Vec<char> = pattern.chars().collect();
+  chars.sort();
+  chars.into_iter().collect()
+}
 
-segments 3 are in all 2,3,5,6,9 digits, but not in 0
+// Wire to segment mapping of one display
+#[derive(Debug, Clone, PartialEq)]
+struct Wiring {
+  segment_by_wire: HashMap<char, char>,
+}
 
-any of digits (2,3,5) - digit 0 gives segment 3
+impl Wiring {
+  fn digit(&self, pattern: &str) -> Option<i32> {
+    let segments: Option<String> = pattern.chars().map(|w|self.segment_by_wire.get(&w).copied()).collect();
+    let segments = sorted(&segments?);
+    SEGMENTS_BY_DIGIT.iter().position(|d|*d == segments).map(|d|d as i32)
+  }
+}
 
-segments (0,2,3,5) are one segment away (6) from digit 3 
+impl fmt::Display for Wiring {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let mappings: Vec<String> = WIRES.chars().map(|w|format!("{}->{}", w, self.segment_by_wire[&w])).collect();
+    write!(f, "{}", mappings.join(" "))
+  }
+}
 
-segments (0,2,3,5,6) are one segment away (1) from digit 9
+fn validate_pattern(pattern: &str) -> Result<(), String> {
+  let wires: HashSet<char> = pattern.chars().collect();
+  if pattern.is_empty() || wires.len() != pattern.len() || !wires.iter().all(|w|WIRES.contains(*w)) {
+    return Err(format!("Invalid pattern {}", pattern));
+  }
+  Ok(())
+}
 
-we get digit 0 by exclusion
+// Fixes wires with a single candidate segment and segments with a single candidate wire
+fn propagate(candidates: &mut HashMap<char, HashSet<char>>) -> Result<(), String> {
+  let mut changed = true;
+  while changed {
+    changed = false;
+    for wire in WIRES.chars() {
+      let segments = &candidates[&wire];
+      if segments.is_empty() {
+        return Err(format!("Inconsistent patterns, no segment left for wire {}", wire));
+      }
+      if segments.len() == 1 {
+        let segment = *segments.iter().next().unwrap();
+        for other in WIRES.chars().filter(|o|*o != wire) {
+          changed |= candidates.get_mut(&other).unwrap().remove(&segment);
+        }
+      }
+    }
+    for segment in WIRES.chars() {
+      let wires: Vec<char> = WIRES.chars().filter(|w|candidates[w].contains(&segment)).collect();
+      if wires.is_empty() {
+        return Err(format!("Inconsistent patterns, no wire left for segment {}", segment));
+      }
+      if wires.len() == 1 && candidates[&wires[0]].len() > 1 {
+        candidates.insert(wires[0], HashSet::from([segment]));
+        changed = true;
+      }
+    }
+  }
+  Ok(())
+}
 
-we get digit 5 given segment (0,1,3,5,6)
+// Collects up to 2 wirings decoding every pattern, enough to tell an ambiguous line
+fn search(
+  patterns: &Vec<String>,
+  candidates: &HashMap<char, HashSet<char>>,
+  wiring: &mut Wiring,
+  solutions: &mut Vec<Wiring>,
+) {
+  if solutions.len() > 1 {
+    return;
+  }
+  let wire = match WIRES.chars().find(|w|!wiring.segment_by_wire.contains_key(w)) {
+    Some(w) => w,
+    None => {
+      if patterns.iter().all(|p|wiring.digit(p).is_some()) {
+        solutions.push(wiring.clone());
+      }
+      return;
+    }
+  };
+  let mut segments: Vec<char> = candidates[&wire].iter().copied().collect();
+  segments.sort();
+  for segment in segments {
+    if wiring.segment_by_wire.values().any(|s|*s == segment) {
+      continue;
+    }
+    wiring.segment_by_wire.insert(wire, segment);
+    search(patterns, candidates, wiring, solutions);
+    wiring.segment_by_wire.remove(&wire);
+  }
+}
 
-we get digit 2 by exclusion
-*/
+fn find_wiring(patterns: &Vec<String>) -> Result<Wiring, String> {
+  let mut candidates: HashMap<char, HashSet<char>> = WIRES
+    .chars()
+    .map(|w|(w, WIRES.chars().collect()))
+    .collect();
+
+  for pattern in patterns {
+    validate_pattern(pattern)?;
+    let digits: Vec<&str> = SEGMENTS_BY_DIGIT.iter().copied().filter(|d|d.len() == pattern.len()).collect();
+    if digits.is_empty() {
+      return Err(format!("No digit has {} segments like {}", pattern.len(), pattern));
+    }
+    // lit wires light one of the digits segments, unlit ones none of their common segments
+    let any_segments: HashSet<char> = digits.iter().flat_map(|d|d.chars()).collect();
+    let common_segments: HashSet<char> = WIRES.chars().filter(|s|digits.iter().all(|d|d.contains(*s))).collect();
+    for (wire, segments) in candidates.iter_mut() {
+      if pattern.contains(*wire) {
+        segments.retain(|s|any_segments.contains(s));
+      } else {
+        segments.retain(|s|!common_segments.contains(s));
+      }
+    }
+  }
+  propagate(&mut candidates)?;
+
+  let mut solutions: Vec<Wiring> = Vec::new();
+  search(patterns, &candidates, &mut Wiring { segment_by_wire: HashMap::new() }, &mut solutions);
+  match solutions.len() {
+    0 => Err("Inconsistent patterns, no wiring decodes them all".to_string()),
+    1 => Ok(solutions.remove(0)),
+    _ => Err(format!("Ambiguous patterns, {} and {} both decode them", solutions[0], solutions[1])),
+  }
+}
 
-#[derive(Clone)]
-struct Digit {
-  value: i32,
-  segments: HashSet<char>,
+// Digit of each pattern, keyed by its sorted wires
+fn find_digits(patterns: &Vec<String>) -> Result<HashMap<String, i32>, String> {
+  let wiring = find_wiring(patterns)?;
+  Ok(patterns.iter().map(|p|(sorted(p), wiring.digit(p).unwrap())).collect())
 }
 
 struct Line {
-  digits: HashMap<String, Digit>,
+  patterns: Vec<String>,
   display: Vec<String>
 }
 
-fn find_digits(digit_data: &Vec<String>) -> HashMap<String, Digit> {
-  let known_len_by_value: HashMap<i32, usize> = HashMap::from([
-    (1, 2),
-    (4, 4),
-    (7, 3),
-    (8, 7),
-  ]);
-
-  let mut digits_by_segment: HashMap<String, Digit> = HashMap::new();
-  let mut digits_by_value: HashMap<i32, Digit> = HashMap::new();
-
-  let mut digits: Vec<String> = digit_data.clone();
-
-  // find 1, 4, 7, 8
-  for (val, len) in known_len_by_value{
-    let index = digits.iter().position(|d| d.len() == len).unwrap();
-    let s = &digits[index].clone();
-    let segments: HashSet<char> = s.chars().collect();
-    let mut chars: Vec<char> = s.chars().collect();
-    chars.sort_by(|a,b| b.cmp(a));
-    digits_by_segment.insert(chars.into_iter().collect(), Digit{value: val, segments: segments});
-    let segments: HashSet<char> = s.chars().collect();
-    digits_by_value.insert(val, Digit{value: val, segments: segments});
-    digits.remove(index);
+impl Line {
+  fn value(&self) -> Result<i32, String> {
+    let digits = find_digits(&self.patterns)?;
+    let mut number = 0;
+    for d in &self.display {
+      match digits.get(&sorted(d)) {
+        Some(digit) => number = digit + number * 10,
+        None => return Err(format!("Display pattern {} is not among the line patterns", d)),
+      }
+    }
+    Ok(number)
   }
+}
 
-  // find 6
-  {
-    let index = digits.iter().position(|d | {
-      if d.len() != 6 {
-        return false;
-      }
-      let d_set: HashSet<char> = d.chars().collect();
-      let delta: HashSet<char> = digits_by_value[&1].segments.difference(&d_set).map(|c|c.clone()).collect();
-      return delta.len() == 1;
-    }).unwrap();
-    let six: &String = &digits[index].clone();
-    let segments: HashSet<char> = six.chars().collect();
-    let mut chars: Vec<char> = six.chars().collect();
-    chars.sort_by(|a,b| b.cmp(a));
-    digits_by_segment.insert(chars.into_iter().collect(), Digit{value: 6, segments: segments});
-    let segments: HashSet<char> = six.clone().chars().collect();
-    digits_by_value.insert(6, Digit{value: 6, segments: segments});
-    digits.remove(index);
+fn parse_line(data: &String) -> Result<Line, String> {
+  let data_splits: Vec<&str> = data.split("|").collect();
+  if data_splits.len() != 2 {
+    return Err(format!("Expected patterns and display separated by a single |, got {:?}", data));
   }
+  let patterns: Vec<String> = data_splits[0].split_whitespace().map(|s|String::from(s)).collect();
 
-  // find 5 
-  {
-    let index = digits.iter().position(|d | {
-      if d.len() != 5 {
-        return false;
-      }
-      let d_set: HashSet<char> = d.chars().collect();
-      let delta: HashSet<char> = digits_by_value[&6].segments.difference(&d_set).map(|c|c.clone()).collect();
-      return delta.len() == 1;
-    }).unwrap();
-    let five: &String = &digits[index].clone();
-    let mut chars: Vec<char> = five.chars().collect();
-    chars.sort_by(|a,b| b.cmp(a));
-    let segments: HashSet<char> = five.clone().chars().collect();
-    digits_by_segment.insert(chars.into_iter().collect(), Digit{value: 5, segments: segments});
-    let segments: HashSet<char> = five.clone().chars().collect();
-    digits_by_value.insert(5, Digit{value: 5, segments: segments});
-    digits.remove(index);
+  let display: Vec<String> = data_splits[1].split_whitespace().map(|s|String::from(s)).collect();
+  return Ok(Line { patterns, display })
+}
+
+// Non blank lines with their 1-based number
+fn numbered_lines(data: &Vec<String>) -> impl Iterator<Item = (usize, &String)> {
+  data.iter().enumerate().filter(|(_, d)|!d.trim().is_empty()).map(|(i, d)|(i + 1, d))
+}
+
+// SplitMix64, enough to generate reproducible inputs without extra dependencies
+struct Random {
+  state: u64,
+}
+
+impl Random {
+  fn new(seed: u64) -> Self {
+    Self { state: seed }
   }
 
-  // find 3
-  {  
-    let index = digits.iter().position(|d | {
-      if d.len() != 5 {
-        return false;
-      }
-      let d_set: HashSet<char> = d.chars().collect();
-      let delta: HashSet<char> = digits_by_value[&5].segments.difference(&d_set).map(|c|c.clone()).collect();
-      return delta.len() == 1;
-    }).unwrap();
-    let three: &String = &digits[index].clone();
-    let mut chars: Vec<char> = three.chars().collect();
-    chars.sort_by(|a,b| b.cmp(a));
-    let segments: HashSet<char> = three.chars().collect();
-    digits_by_segment.insert(chars.into_iter().collect(),  Digit{value: 3, segments: segments});
-    let segments: HashSet<char> = three.chars().collect();
-    digits_by_value.insert(3,  Digit{value: 3, segments: segments});
-    digits.remove(index);
+  fn next(&mut self) -> u64 {
+    self.state = self.state.wrapping_add(0x9E3779B97F4A7C15);
+    let mut z = self.state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^ (z >> 31)
   }
 
-  // find 2
-  {
-    let index = digits.iter().position(|d | d.len() == 5).unwrap();
-    let two: &String = &digits[index].clone();
-    let mut chars: Vec<char> = two.chars().collect();
-    chars.sort_by(|a,b| b.cmp(a));
-    let segments: HashSet<char> = two.chars().collect();
-    digits_by_segment.insert(chars.into_iter().collect(), Digit{value: 2, segments: segments});
-    let segments: HashSet<char> = two.chars().collect();
-    digits_by_value.insert(2, Digit{value: 2, segments: segments});
-    digits.remove(index);
+  fn below(&mut self, n: usize) -> usize {
+    (self.next() % n as u64) as usize
   }
 
-  // find 9
-  {
-    let index = digits.iter().position(|d | {
-      if d.len() != 6 {
-        return false;
-      }
-      let d_set: HashSet<char> = d.chars().collect();
-      let delta: HashSet<char> = digits_by_value[&5].segments.difference(&d_set).map(|c|c.clone()).collect();
-      return delta.len() == 0;
-    }).unwrap();
-    let nine: &String = &digits[index].clone();
-    let mut chars: Vec<char> = nine.chars().collect();
-    chars.sort_by(|a,b| b.cmp(a));
-    let segments: HashSet<char> = nine.chars().collect();
-    digits_by_value.insert(9, Digit{value: 9, segments: segments});
-    let segments: HashSet<char> = nine.chars().collect();
-    digits_by_segment.insert(chars.into_iter().collect(), Digit{value: 9, segments: segments});
-    digits.remove(index);
+  // Fisher-Yates
+  fn shuffle<T>(&mut self, items: &mut Vec<T>) {
+    for i in (1..items.len()).rev() {
+      let j = self.below(i + 1);
+      items.swap(i, j);
+    }
   }
+}
+
+struct GeneratedLine {
+  line: String,
+  // ground truth
+  wiring: Wiring,
+  value: i32,
+}
 
-  // find 0
-  {
-    let index = 0;
-    let zero: &String = &digits[0].clone();
-    let mut chars: Vec<char> = zero.chars().collect();
-    chars.sort_by(|a,b| b.cmp(a));
-    let segments: HashSet<char> = zero.chars().collect();
-    digits_by_value.insert(0, Digit{value: 0, segments: segments});
-    let segments: HashSet<char> = zero.chars().collect();
-    digits_by_segment.insert(chars.into_iter().collect(), Digit{value: 0, segments: segments});
-    digits.remove(index);
+// Random wiring, the ten digits patterns shuffled and a four digits display
+fn generate_line(random: &mut Random) -> GeneratedLine {
+  let mut wires: Vec<char> = WIRES.chars().collect();
+  random.shuffle(&mut wires);
+  let wire_by_segment: HashMap<char, char> = WIRES.chars().zip(wires.iter().copied()).collect();
+  let segment_by_wire: HashMap<char, char> = wire_by_segment.iter().map(|(s, w)|(*w, *s)).collect();
+
+  let render = |digit: usize, random: &mut Random| -> String {
+    let mut pattern: Vec<char> = SEGMENTS_BY_DIGIT[digit].chars().map(|s|wire_by_segment[&s]).collect();
+    random.shuffle(&mut pattern);
+    pattern.into_iter().collect()
+  };
+
+  let mut digits: Vec<usize> = (0..10).collect();
+  random.shuffle(&mut digits);
+  let patterns: Vec<String> = digits.iter().map(|d|render(*d, random)).collect();
+
+  let display_digits: Vec<usize> = (0..4).map(|_|random.below(10)).collect();
+  let display: Vec<String> = display_digits.iter().map(|d|render(*d, random)).collect();
+  let value = display_digits.iter().fold(0, |number, d|number * 10 + *d as i32);
+
+  GeneratedLine {
+    line: format!("{} | {}", patterns.join(" "), display.join(" ")),
+    wiring: Wiring { segment_by_wire },
+    value,
   }
+}
 
-  return digits_by_segment;
+fn generate_input(seed: u64, lines: usize) -> Vec<GeneratedLine> {
+  let mut random = Random::new(seed);
+  (0..lines).map(|_|generate_line(&mut random)).collect()
 }
 
-fn parse_line(data: &String) -> Line {
-  let data_splits: Vec<&str> = data.split("|").collect();
-  let digits_data: Vec<String> = data_splits[0].split_whitespace().map(|s|String::from(s)).collect();
-  
-  let display: Vec<String> = data_splits[1].split_whitespace().map(|s|String::from(s)).collect();
-  return Line { digits: find_digits(&digits_data), display: display }
+// Property check: decoding a generated line recovers its wiring and value
+fn check_generated(generated: &Vec<GeneratedLine>) -> Result<(), String> {
+  for (i, g) in generated.iter().enumerate() {
+    let line = parse_line(&g.line).map_err(|e|format!("Line {}: {}", i + 1, e))?;
+    let wiring = find_wiring(&line.patterns).map_err(|e|format!("Line {}: {}", i + 1, e))?;
+    if wiring != g.wiring {
+      return Err(format!("Line {}: found wiring {} instead of {}", i + 1, wiring, g.wiring));
+    }
+    let value = line.value().map_err(|e|format!("Line {}: {}", i + 1, e))?;
+    if value != g.value {
+      return Err(format!("Line {}: found value {} instead of {}", i + 1, value, g.value));
+    }
+  }
+  Ok(())
 }
 
-fn solution_1(data: &Vec<String>) -> i32 {
+fn generated_truth_to_csv(generated: &Vec<GeneratedLine>) -> String {
+  let mut csv = "line,value,wiring\n".to_owned();
+  for (i, g) in generated.iter().enumerate() {
+    csv += &format!("{},{},{}\n", i + 1, g.value, g.wiring);
+  }
+  return csv;
+}
+
+fn solution_1(data: &Vec<String>) -> Result<i32, String> {
   let mut counter = 0;
 
   let unique_length: HashSet<&i32> = [2,3,4,7].iter().clone().collect();
 
-  for d in data {
-    let line: Line = parse_line(d);
+  for (n, d) in numbered_lines(data) {
+    let line: Line = parse_line(d).map_err(|e|format!("Line {}: {}", n, e))?;
 
     for d in line.display {
       if unique_length.contains(&(d.len() as i32)) {
@@ -258,52 +339,146 @@ fn solution_1(data: &Vec<String>) -> i32 {
     }
   }
 
-  return counter;
+  return Ok(counter);
 }
 
 
-fn solution_2(data: &Vec<String>) -> i32 {
+fn solution_2(data: &Vec<String>) -> Result<i32, String> {
   let mut counter = 0;
 
-  for d in data {
-    let mut number = 0;
-    let line: Line = parse_line(d);
+  for (n, d) in numbered_lines(data) {
+    let line: Line = parse_line(d).map_err(|e|format!("Line {}: {}", n, e))?;
+    counter += line.value().map_err(|e|format!("Line {}: {}", n, e))?;
+  }
 
-    for d in line.display {
-      let mut chars: Vec<char> = d.chars().collect();
-      chars.sort_by(|a,b| b.cmp(a));
-      let s: String = chars.into_iter().collect();
-      number = line.digits.get(&s).unwrap().value + number * 10; 
-    }
+  return Ok(counter);
+}
 
-    counter += number;
+fn print_wirings(data: &Vec<String>) {
+  for (n, d) in numbered_lines(data) {
+    match parse_line(d).and_then(|line|find_wiring(&line.patterns)) {
+      Ok(wiring) => println!("Line {}: {}", n, wiring),
+      Err(e) => println!("Line {}: {}", n, e),
+    }
   }
-
-  return counter;
 }
 
 fn main() {
   let test = false;
+  // print the wire to segment mapping found for each line
+  let show_wirings = false;
+  // generate a random input from a seed and a number of lines, check it decodes to its ground truth
+  let generate: Option<(u64, usize)> = None;
+  let data: Vec<String>;
   if test {
     let file = File::open("inputs/day08.test.txt").unwrap();
-    let data: Vec<String> = io::BufReader::new(file)
+    data = io::BufReader::new(file)
                 .lines()
                 .map(|line|line.unwrap())
                 .collect();
 
-    println!(r#"🧪 Part 1 test result is {:?}"#, solution_1(&data));
+    match solution_1(&data) {
+      Ok(result) => println!(r#"🧪 Part 1 test result is {:?}"#, result),
+      Err(e) => println!(r#"❌ Part 1 test failed: {}"#, e),
+    }
 
-    println!(r#"🧪 Part 2 test result is {:?}"#, solution_2(&data));
+    match solution_2(&data) {
+      Ok(result) => println!(r#"🧪 Part 2 test result is {:?}"#, result),
+      Err(e) => println!(r#"❌ Part 2 test failed: {}"#, e),
+    }
   } else {
 
     let file = File::open("inputs/day08.txt").unwrap();
-    let data: Vec<String> = io::BufReader::new(file)
+    data = io::BufReader::new(file)
                 .lines()
                 .map(|line|line.unwrap())
                 .collect();
 
-    println!(r#"🎉 Part 1 result is {:?}"#, solution_1(&data));
+    match solution_1(&data) {
+      Ok(result) => println!(r#"🎉 Part 1 result is {:?}"#, result),
+      Err(e) => println!(r#"❌ Part 1 failed: {}"#, e),
+    }
+
+    match solution_2(&data) {
+      Ok(result) => println!(r#"🎉 Part 2 result is {:?}"#, result),
+      Err(e) => println!(r#"❌ Part 2 failed: {}"#, e),
+    }
+  }
+
+  if show_wirings {
+    print_wirings(&data);
+  }
+
+  if let Some((seed, lines)) = generate {
+    let generated = generate_input(seed, lines);
+    let input: Vec<String> = generated.iter().map(|g|g.line.clone()).collect();
+    fs::create_dir_all("outputs").unwrap();
+    fs::write("outputs/day08.generated.txt", input.join("\n") + "\n").unwrap();
+    fs::write("outputs/day08.generated.truth.csv", generated_truth_to_csv(&generated)).unwrap();
+    match check_generated(&generated) {
+      Ok(()) => println!("{} generated lines decoded to their ground truth", lines),
+      Err(e) => println!("❌ Generated input check failed: {}", e),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn find_digits_recovers_generated_ground_truth() {
+    for seed in [0, 1, 7, 42, 2021, u64::MAX] {
+      for g in generate_input(seed, 100) {
+        let line = parse_line(&g.line).unwrap();
+        assert_eq!(line.patterns.len(), 10);
+        assert_eq!(line.display.len(), 4);
+
+        assert_eq!(find_wiring(&line.patterns).unwrap(), g.wiring, "seed {}: {}", seed, g.line);
+        let digits = find_digits(&line.patterns).unwrap();
+        let value = line.display.iter().fold(0, |number, d|number * 10 + digits[&sorted(d)]);
+        assert_eq!(value, g.value, "seed {}: {}", seed, g.line);
+        assert_eq!(line.value(), Ok(g.value));
+      }
+    }
+  }
 
-    println!(r#"🎉 Part 2 result is {:?}"#, solution_2(&data));
+  #[test]
+  fn generated_input_is_reproducible() {
+    let first: Vec<String> = generate_input(42, 50).into_iter().map(|g|g.line).collect();
+    let second: Vec<String> = generate_input(42, 50).into_iter().map(|g|g.line).collect();
+    let other: Vec<String> = generate_input(43, 50).into_iter().map(|g|g.line).collect();
+    assert_eq!(first, second);
+    assert_ne!(first, other);
+  }
+  fn line_error(data: &str) -> String {
+    parse_line(&data.to_string()).and_then(|line|line.value()).unwrap_err()
   }
-}
\ No newline at end of file
+
+  #[test]
+  fn reports_ambiguous_line() {
+    assert!(line_error("ab | ab").starts_with("Ambiguous patterns"));
+  }
+
+  #[test]
+  fn reports_inconsistent_line() {
+    // abc can't be the 7 when abcd is the 4, the 7 top segment isn't in the 4
+    assert!(line_error("ab abc abcd abcdefg | ab").starts_with("Inconsistent patterns"));
+    // two different patterns for the 1
+    assert!(line_error("ab bc | ab").starts_with("Inconsistent patterns"));
+  }
+
+  #[test]
+  fn reports_invalid_pattern() {
+    assert_eq!(line_error("aa | aa"), "Invalid pattern aa");
+    assert_eq!(line_error("abz | ab"), "Invalid pattern abz");
+    assert!(line_error("abcdefgh | ab").starts_with("Invalid pattern"));
+  }
+
+  #[test]
+  fn reports_line_without_separator() {
+    assert!(parse_line(&"ab abc abcd".to_string()).is_err());
+    assert!(parse_line(&"ab | abc | abcd".to_string()).is_err());
+    assert!(solution_2(&vec!["ab abc abcd".to_string()]).unwrap_err().starts_with("Line 1:"));
+  }
+}
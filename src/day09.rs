@@ -1,6 +1,5 @@
 use std::fs::File;
 use std::io::{self, BufRead};
-use std::collections::HashSet;
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 struct Point {
@@ -67,40 +66,63 @@ impl Heightmap {
     return neighbours;
   }
 
-  fn evaluate_basin_size(&mut self, low: Point) -> u32 {
-    let mut visited: HashSet<Point> = HashSet::new();
-    let mut basin: Vec<Point> = Vec::new();
-    basin.push(low);
-    visited.insert(low);
-  
-    let mut index = 0;
-    while index < basin.len() {
-      let current: Point = basin[index].clone();
-      let current_value = self.matrix[current.row][current.col].value;
-
-      let neighbours = self.get_neighbours(current);
-      for n in neighbours {
-        if visited.contains(&n.position) {
+  // Flood fills every region bounded by height 9 walls, whatever the slopes inside
+  fn label_basins(&self) -> Basins {
+    let mut labels: Vec<Vec<Option<usize>>> = vec![vec![None; self.tot_cols]; self.tot_rows];
+    let mut sizes: Vec<u32> = Vec::new();
+
+    for row in 0..self.tot_rows {
+      for col in 0..self.tot_cols {
+        if labels[row][col].is_some() || self.matrix[row][col].value == 9 {
           continue;
         }
-        if n.value == 9 {
-          continue
-        }
-        if n.value <= current_value {
-          continue;
+        let id = sizes.len();
+        let mut size = 0;
+        let mut to_visit: Vec<Point> = vec![Point{row, col}];
+        labels[row][col] = Some(id);
+        while let Some(current) = to_visit.pop() {
+          size += 1;
+          for n in self.get_neighbours(current) {
+            if n.value == 9 || labels[n.position.row][n.position.col].is_some() {
+              continue;
+            }
+            labels[n.position.row][n.position.col] = Some(id);
+            to_visit.push(n.position);
+          }
         }
-        visited.insert(n.position);
-        basin.push(n.position.clone());
+        sizes.push(size);
       }
-
-      index += 1
     }
 
-  
-    return basin.len() as u32;
+    return Basins { labels, sizes };
   }
 }
 
+struct Basins {
+  // basin id of each cell, None for walls
+  labels: Vec<Vec<Option<usize>>>,
+  // indexed by basin id
+  sizes: Vec<u32>,
+}
+
+impl Basins {
+  // Heights coloured by basin, walls left uncoloured
+  fn render(&self, heightmap: &Heightmap) -> String {
+    let colours = [31, 32, 33, 34, 35, 36, 91, 92, 93, 94, 95, 96];
+    let mut s = String::new();
+    for (row, labels) in self.labels.iter().enumerate() {
+      for (col, label) in labels.iter().enumerate() {
+        let value = heightmap.matrix[row][col].value;
+        match label {
+          Some(id) => s += &format!("\x1b[1;{}m{}\x1b[0m", colours[id % colours.len()], value),
+          None => s += &format!("\x1b[2m{}\x1b[0m", value),
+        }
+      }
+      s += "\n";
+    }
+    return s;
+  }
+}
 
 fn solution_1(data: &Vec<String>) -> u32 {
   let mut counter = 0;
@@ -121,26 +143,9 @@ fn solution_1(data: &Vec<String>) -> u32 {
 }
 
 fn solution_2(data: &Vec<String>) -> u32 {
+  let hm = Heightmap::parse(data);
 
-  let mut hm = Heightmap::parse(data);
-  let mut lows: Vec<Point> = Vec::new();
-
-  for row in 0..hm.tot_rows {
-    for col in 0..hm.tot_cols {
-      let val: u32 = hm.matrix[row][col].value;
-      let neighbours = hm.get_neighbours(Point{row, col});
-      if neighbours.iter().filter(|x| x.value <= val).count() == 0 {
-        lows.push(Point{row, col});
-      }
-    }
-  }
-
-  let mut sizes: Vec<u32> = Vec::new();
-  // recursion on lows
-  for low in lows{
-    let size = hm.evaluate_basin_size(low);
-    sizes.push(size);
-  }  
+  let mut sizes: Vec<u32> = hm.label_basins().sizes;
   sizes.sort();
 
   let mut ret = 1;
@@ -153,9 +158,12 @@ fn solution_2(data: &Vec<String>) -> u32 {
 
 fn main() {
   let test = false;
+  // print the heightmap coloured by basin
+  let show_basins = false;
+  let data: Vec<String>;
   if test {
     let file = File::open("inputs/day09.test.txt").unwrap();
-    let data: Vec<String> = io::BufReader::new(file)
+    data = io::BufReader::new(file)
                 .lines()
                 .map(|line|line.unwrap())
                 .collect();
@@ -166,7 +174,7 @@ fn main() {
   } else {
 
     let file = File::open("inputs/day09.txt").unwrap();
-    let data: Vec<String> = io::BufReader::new(file)
+    data = io::BufReader::new(file)
                 .lines()
                 .map(|line|line.unwrap())
                 .collect();
@@ -175,4 +183,11 @@ fn main() {
 
     println!(r#"🎉 Part 2 result is {:?}"#, solution_2(&data));
   }
-}
\ No newline at end of file
+
+  if show_basins {
+    let hm = Heightmap::parse(&data);
+    let basins = hm.label_basins();
+    print!("{}", basins.render(&hm));
+    println!("{} basins, sizes {:?}", basins.sizes.len(), basins.sizes);
+  }
+}
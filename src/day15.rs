@@ -47,16 +47,6 @@ struct ChitonMatrix {
 }
 
 impl ChitonMatrix {
-  fn new() -> Self {
-    Self {
-      chitons_by_point: HashMap::new(),
-      size: Size {
-        tot_cols: 0,
-        tot_rows: 0,
-      }
-    }
-  }
-
   fn parse(data: &Vec<String>) -> Self {
     let mut chitons_by_point: HashMap<Point, u64> = HashMap::new();
     
@@ -79,51 +69,237 @@ impl ChitonMatrix {
   }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Movement {
+  // up, down, left and right
+  Orthogonal,
+  // diagonals too
+  Diagonal,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum StepCost {
+  // pay the risk of the point entered, as in the puzzle
+  Enter,
+  // pay the risk of the point left
+  Leave,
+}
+
 #[derive(Clone, Debug)]
-struct DijkstraMatrix {
-  chitons_by_point: HashMap<Point, u64>,
-  risk_from_source_by_point: HashMap<Point, u64>,
-  previous_by_point: HashMap<Point, Point>,
-  points_matrix: Vec<Vec<Point>>,
+struct RouteOptions {
+  movement: Movement,
+  step_cost: StepCost,
 }
 
-impl DijkstraMatrix {
-  fn init(chiton_matrix: &ChitonMatrix) -> Self {
-    let chitons_by_point: HashMap<Point, u64> = chiton_matrix.chitons_by_point.clone();
-    let mut risk_from_source_by_point: HashMap<Point, u64> = HashMap::new();
-    let mut points_matrix: Vec<Vec<Point>> = Vec::new();
-    // parse matrix
-    let tot_rows = chiton_matrix.size.tot_rows;
-    let tot_cols = chiton_matrix.size.tot_cols;
-    for row in 0..tot_rows {
-      let mut points_row: Vec<Point> = Vec::new();
-      for col in 0..tot_cols{
-        let point = Point{row, col};
-        points_row.push(point.clone());
-        risk_from_source_by_point.insert(point.clone(), u64::MAX);
+impl Default for RouteOptions {
+  fn default() -> Self {
+    Self {
+      movement: Movement::Orthogonal,
+      step_cost: StepCost::Enter,
+    }
+  }
+}
+
+// Chiton risks of the base tile in a flat array, row by row. The cave repeats the
+// tile, adding 1 to the risk for each tile right or down, wrapping back to 1 past max_risk
+#[derive(Clone, Debug)]
+struct Cave {
+  risks: Vec<u64>,
+  tile_size: Size,
+  max_risk: u64,
+  size: Size,
+}
+
+impl Cave {
+  fn from_matrix(matrix: &ChitonMatrix) -> Self {
+    // digits are at most 9, a single tile never wraps
+    Self::tiled(matrix, 1, 9).unwrap()
+  }
+
+  // Risks of 0 are kept on the base tile, repeated tiles need risks from 1 to max_risk to wrap
+  fn tiled(matrix: &ChitonMatrix, tiles: usize, max_risk: u64) -> Result<Self, String> {
+    if tiles == 0 {
+      return Err("The cave needs at least 1 tile".to_string());
+    }
+    if max_risk == 0 {
+      return Err("Maximum risk must be at least 1".to_string());
+    }
+    if matrix.size.tot_rows == 0 || matrix.size.tot_cols == 0 {
+      return Err("The base tile is empty".to_string());
+    }
+    let min_risk = if tiles > 1 { 1 } else { 0 };
+    let mut risks: Vec<u64> = Vec::with_capacity(matrix.size.tot_rows * matrix.size.tot_cols);
+    for row in 0..matrix.size.tot_rows {
+      for col in 0..matrix.size.tot_cols {
+        let risk = *matrix.chitons_by_point.get(&Point{row, col}).unwrap();
+        if risk < min_risk || risk > max_risk {
+          return Err(format!("Risk {} at ({}, {}) is out of the range {} to {}", risk, row, col, min_risk, max_risk));
+        }
+        risks.push(risk);
       }
-      points_matrix.push(points_row);
     }
+    Ok(Self {
+      risks,
+      tile_size: matrix.size.clone(),
+      max_risk,
+      size: Size {
+        tot_cols: tiles * matrix.size.tot_cols,
+        tot_rows: tiles * matrix.size.tot_rows,
+      },
+    })
+  }
 
-    let previous_by_point: HashMap<Point, Point> = HashMap::new();
-    return Self {
-      chitons_by_point,
-      points_matrix,
-      risk_from_source_by_point,
-      previous_by_point,
+  fn index(&self, point: &Point) -> usize {
+    point.row * self.size.tot_cols + point.col
+  }
+
+  fn point(&self, index: usize) -> Point {
+    Point { row: index / self.size.tot_cols, col: index % self.size.tot_cols }
+  }
+
+  fn risk(&self, point: &Point) -> u64 {
+    let tile_row = point.row % self.tile_size.tot_rows;
+    let tile_col = point.col % self.tile_size.tot_cols;
+    let increment = (point.row / self.tile_size.tot_rows + point.col / self.tile_size.tot_cols) as u64;
+    let value = self.risks[tile_row * self.tile_size.tot_cols + tile_col];
+    if increment == 0 {
+      return value;
     }
+    (value + increment - 1) % self.max_risk + 1
   }
 
-  fn print(&self) {
-    println!("====== Chitons ========");
-    for row in self.points_matrix.clone() {
-      let mut s = "".to_owned();
-      for p in row {
-        s += self.chitons_by_point.get(&p).unwrap().to_string().as_str();
+  fn neighbours(&self, point: &Point, movement: Movement) -> Vec<Point> {
+    let mut neighbours = get_neighbours(point, &self.size);
+    if movement == Movement::Diagonal {
+      for (d_row, d_col) in [(-1, -1), (-1, 1), (1, -1), (1, 1)] {
+        let row = point.row as i64 + d_row;
+        let col = point.col as i64 + d_col;
+        if row >= 0 && col >= 0 && (row as usize) < self.size.tot_rows && (col as usize) < self.size.tot_cols {
+          neighbours.push(Point{row: row as usize, col: col as usize});
+        }
       }
-      println!("{}",s);
+    }
+    return neighbours;
+  }
+
+  fn step_cost(&self, from: &Point, to: &Point, step_cost: StepCost) -> u64 {
+    match step_cost {
+      StepCost::Enter => self.risk(to),
+      StepCost::Leave => self.risk(from),
+    }
+  }
+
+  // Every step costs at least 1, so the number of steps left never overestimates
+  fn min_steps(from: &Point, to: &Point, movement: Movement) -> u64 {
+    let d_row = from.row.abs_diff(to.row) as u64;
+    let d_col = from.col.abs_diff(to.col) as u64;
+    match movement {
+      Movement::Orthogonal => d_row + d_col,
+      Movement::Diagonal => u64::max(d_row, d_col),
     }
   }
+
+  // A* from source to target, or Dijkstra over the whole cave without target.
+  // Returns risk from source and previous point index for every point, u32::MAX if not reached.
+  // u32 keeps the search arrays small on big caves: the cave must have fewer than u32::MAX
+  // cells and risks must stay below u32::MAX, it panics otherwise rather than truncating
+  fn search(&self, source: &Point, target: Option<&Point>, options: &RouteOptions) -> (Vec<u32>, Vec<u32>) {
+    let total = self.size.tot_rows * self.size.tot_cols;
+    if u32::try_from(total).map_or(true, |t|t == u32::MAX) {
+      panic!("Cave of {} cells is too big, at most {} cells can be searched", total, u32::MAX - 1);
+    }
+    let mut risk_from_source: Vec<u32> = vec![u32::MAX; total];
+    let mut previous: Vec<u32> = vec![u32::MAX; total];
+    let mut heap: BinaryHeap<State> = BinaryHeap::new();
+    let heuristic = |p: &Point| match target {
+      Some(t) => Self::min_steps(p, t, options.movement),
+      None => 0,
+    };
+
+    risk_from_source[self.index(source)] = 0;
+    heap.push(State {
+      point: *source,
+      cost: 0,
+      estimate: heuristic(source),
+    });
+
+    while let Some(State { point, cost, .. }) = heap.pop() {
+      if Some(&point) == target {
+        break;
+      }
+
+      let index = self.index(&point);
+      if cost > risk_from_source[index] as u64 {
+        continue;
+      }
+
+      for n in self.neighbours(&point, options.movement) {
+        let next_cost = cost + self.step_cost(&point, &n, options.step_cost);
+        let next_index = self.index(&n);
+        if next_cost < risk_from_source[next_index] as u64 {
+          risk_from_source[next_index] = match u32::try_from(next_cost) {
+            Ok(risk) if risk < u32::MAX => risk,
+            _ => panic!("Risk {} to {:?} is too high, risks must stay below {}", next_cost, n, u32::MAX),
+          };
+          previous[next_index] = index as u32;
+          heap.push(State {
+            point: n,
+            cost: next_cost,
+            estimate: next_cost + heuristic(&n),
+          });
+        }
+      }
+    }
+
+    return (risk_from_source, previous);
+  }
+
+  // Returns the total risk and the path from source to target, both included
+  fn a_star(&self, source: &Point, target: &Point, options: &RouteOptions) -> Option<(u64, Vec<Point>)> {
+    let (risk_from_source, previous) = self.search(source, Some(target), options);
+    let mut index = self.index(target);
+    if risk_from_source[index] == u32::MAX {
+      return None;
+    }
+
+    let mut path: Vec<Point> = vec![*target];
+    while previous[index] != u32::MAX {
+      index = previous[index] as usize;
+      path.push(self.point(index));
+    }
+    path.reverse();
+    return Some((risk_from_source[self.index(target)] as u64, path));
+  }
+
+  // Lowest risk from source to every point of the cave
+  fn risks_from(&self, source: &Point, options: &RouteOptions) -> HashMap<Point, u64> {
+    let (risk_from_source, _) = self.search(source, None, options);
+    return risk_from_source
+      .iter()
+      .enumerate()
+      .filter(|(_, risk)|**risk != u32::MAX)
+      .map(|(index, risk)|(self.point(index), *risk as u64))
+      .collect();
+  }
+
+  // Risk levels with the path highlighted
+  fn render_path(&self, path: &[Point]) -> String {
+    let on_path: HashSet<&Point> = path.iter().collect();
+    let mut s = "".to_owned();
+    for row in 0..self.size.tot_rows {
+      for col in 0..self.size.tot_cols {
+        let point = Point{row, col};
+        let risk = self.risk(&point);
+        if on_path.contains(&point) {
+          s += &format!("\x1b[1;31m{}\x1b[0m", risk);
+        } else {
+          s += &risk.to_string();
+        }
+      }
+      s.push('\n');
+    }
+    return s;
+  }
 }
 
 fn string_to_numbers(s: &String) -> Vec<u64> {
@@ -163,11 +339,13 @@ fn get_neighbours(point: &Point, size: &Size) -> Vec<Point> {
 struct State {
     point: Point,
     cost: u64,
+    // cost plus the heuristic estimate of the cost left
+    estimate: u64,
 }
  
 impl Ord for State {
     fn cmp(&self, other: &Self) -> Ordering {
-        other.cost.cmp(&self.cost)
+        other.estimate.cmp(&self.estimate)
     }
 }
  
@@ -177,113 +355,62 @@ impl PartialOrd for State {
     }
 }
 
-
-
-fn dijkstra_shortest_path(origin_matrix: &DijkstraMatrix, source: &Point, target: &Point) -> u64 {
-  let mut matrix = origin_matrix.clone();
-  let mut heap: BinaryHeap<State> = BinaryHeap::new();
-  let mut visited: HashSet<Point> = HashSet::new();
-  let points_matrix = matrix.points_matrix.clone();
- 
-  // initially we now source to source distance cost is 0
-  matrix.risk_from_source_by_point.insert(source.clone(), 0);
-  heap.push(State{
-    point: source.clone(),
-    cost: 0
-  });
-
-  while let Some(State { point, cost }) = heap.pop() {
-    // find cheapest point from source
-    if point == *target {
-      break;
-    }
-
-    visited.insert(point.clone());
-
-    let size: Size = Size { 
-      tot_cols: points_matrix.first().unwrap().len(), 
-      tot_rows: points_matrix.len(), 
-    };
-
-    if cost > *matrix.risk_from_source_by_point.get(&point).unwrap() {
-      continue;
-    }
-
-    let neighbours: Vec<Point> = get_neighbours(&point.clone(), &size); 
-    for n in neighbours {
-      if visited.contains(&n) {
-        // we already have a shortest path to this point
-        // skip it
-        continue;
-      }
-
-      let next = State {
-        point: n.clone(),
-        cost: cost + matrix.chitons_by_point.get(&n).unwrap(),
-      };
-      if next.cost < *matrix.risk_from_source_by_point.get(&next.point).unwrap() {
-          matrix.risk_from_source_by_point.insert(n.clone(), next.cost);
-          matrix.previous_by_point.insert(n.clone(), point.clone());
-          heap.push(next);
-      }
-    }
-  }
-
-  return matrix.risk_from_source_by_point.get(target).unwrap().clone();
-}
-
 fn solution_1(data: &Vec<String>) -> u64 {
   let chiton_matrix = ChitonMatrix::parse(data);
-  let matrix = DijkstraMatrix::init(&chiton_matrix);
+  let cave = Cave::from_matrix(&chiton_matrix);
 
   let source = Point{row:0, col:0};
-  let target: Point = matrix.points_matrix.last().unwrap().last().unwrap().clone();
+  let target: Point = Point{row: cave.size.tot_rows - 1, col: cave.size.tot_cols - 1};
 
-  return dijkstra_shortest_path(&matrix, &source, &target);
+  let (risk, _) = cave.a_star(&source, &target, &RouteOptions::default()).unwrap();
+  return risk;
 }
 
-fn solution_2(data: &Vec<String>) -> u64 {
-  let sub_matrix = ChitonMatrix::parse(data);
+fn solution_2(data: &Vec<String>, tiles: usize, max_risk: u64) -> Result<u64, String> {
+  let cave = Cave::tiled(&ChitonMatrix::parse(data), tiles, max_risk)?;
 
-  let mut matrix: ChitonMatrix = ChitonMatrix::new();
-  matrix.size.tot_cols = 5 * sub_matrix.size.tot_cols;
-  matrix.size.tot_rows = 5 * sub_matrix.size.tot_rows;
+  let source = Point{row:0, col:0};
+  let target: Point = Point{row: cave.size.tot_rows - 1, col: cave.size.tot_cols - 1};
 
-  for row in 0 .. matrix.size.tot_rows  {
-    for col in 0 .. matrix.size.tot_cols {
-      let point = Point {row, col};
+  let (risk, _) = cave.a_star(&source, &target, &RouteOptions::default()).unwrap();
+  return Ok(risk);
+}
 
-      if sub_matrix.chitons_by_point.contains_key(&point) {
-        matrix.chitons_by_point.insert(point, sub_matrix.chitons_by_point.get(&point).unwrap().clone());
-        continue;
-      }     
-      // find matching point in sub_matrix
-      let sub_row = row % sub_matrix.size.tot_rows;
-      let increment = row / sub_matrix.size.tot_rows;
-
-      let sub_col = col % sub_matrix.size.tot_cols;
-      let increment = increment +  col / sub_matrix.size.tot_cols;
-
-      let mut value = sub_matrix.chitons_by_point.get(&Point{row: sub_row, col: sub_col}).unwrap().clone();
-      value += increment as u64;
-      while value > 9 {
-        value -= 9
-      }
+fn print_path(data: &Vec<String>) {
+  let cave = Cave::from_matrix(&ChitonMatrix::parse(data));
+  let source = Point{row:0, col:0};
+  let target: Point = Point{row: cave.size.tot_rows - 1, col: cave.size.tot_cols - 1};
+
+  let (risk, path) = cave.a_star(&source, &target, &RouteOptions::default()).unwrap();
+  println!("====== Lowest risk path: {} ========", risk);
+  print!("{}", cave.render_path(&path));
+}
 
-      matrix.chitons_by_point.insert(point, value);
+fn print_routes(data: &Vec<String>) {
+  let cave = Cave::from_matrix(&ChitonMatrix::parse(data));
+  let top_left = Point{row:0, col:0};
+  let bottom_right: Point = Point{row: cave.size.tot_rows - 1, col: cave.size.tot_cols - 1};
+  let top_right: Point = Point{row: 0, col: cave.size.tot_cols - 1};
+
+  for movement in [Movement::Orthogonal, Movement::Diagonal] {
+    for step_cost in [StepCost::Enter, StepCost::Leave] {
+      let options = RouteOptions { movement, step_cost };
+      let (to_top_left, _) = cave.a_star(&bottom_right, &top_left, &options).unwrap();
+      let (to_top_right, _) = cave.a_star(&top_left, &top_right, &options).unwrap();
+      let risks = cave.risks_from(&top_left, &options);
+      let max_risk = risks.values().max().unwrap();
+      println!("{:?} moves paying on {:?}: bottom right to top left {}, top left to top right {}, farthest point from top left {}",
+        movement, step_cost, to_top_left, to_top_right, max_risk);
     }
   }
-
-  let dijkstra_matrix = DijkstraMatrix::init(&matrix);
-
-  let source = Point{row:0, col:0};
-  let target: Point = Point{row: matrix.size.tot_rows - 1, col: matrix.size.tot_cols - 1};
-
-  return dijkstra_shortest_path(&dijkstra_matrix, &source, &target);
 }
 
 fn main() {
   let test = false;
+  let show_path = false;
+  let show_routes = false;
+  let tiles = 5;
+  let max_risk = 9;
   let mut file_path: String = "inputs/day15".to_string();
   let mut emoji: String = "🎉".to_string();
   if test {
@@ -300,5 +427,16 @@ fn main() {
 
   println!(r#"{:?} Part 1 result is {:?}"#, emoji, solution_1(&data));
 
-  println!(r#"{:?} Part 2 test result is {:?}"#, emoji, solution_2(&data));
+  match solution_2(&data, tiles, max_risk) {
+    Ok(result) => println!(r#"{:?} Part 2 test result is {:?}"#, emoji, result),
+    Err(e) => println!("❌ Part 2 failed: {}", e),
+  }
+
+  if show_path {
+    print_path(&data);
+  }
+
+  if show_routes {
+    print_routes(&data);
+  }
 }
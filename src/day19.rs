@@ -1,6 +1,6 @@
 use std::collections::{HashMap, HashSet};
 use std::fmt;
-use std::fs::File;
+use std::fs::{self, File};
 use std::hash::Hash;
 use std::io::{self, BufRead};
 use std::iter::Scan;
@@ -9,6 +9,8 @@ use std::thread::park;
 // Solution inspired by 
 // https://www.reddit.com/r/adventofcode/comments/rjpf7f/comment/hp7tpyf/?utm_source=share&utm_medium=web2x&context=3
 
+const MIN_OVERLAP: usize = 12;
+
 #[derive(Hash, PartialEq, Eq, Clone, Debug)]
 struct Point {
   x: i64,
@@ -44,6 +46,17 @@ impl Point {
     return i64::abs(d_x) + i64::abs(d_y) + i64::abs(d_z);
   }
 
+  fn coordinates(&self) -> [i64; 3] {
+    [self.x, self.y, self.z]
+  }
+
+  fn squared_distance(&self, other: &Point) -> i64 {
+    let d_x = self.x - other.x;
+    let d_y = self.y - other.y;
+    let d_z = self.z - other.z;
+    return d_x * d_x + d_y * d_y + d_z * d_z;
+  }
+
   fn offset_from(&self, other: &Point) -> Point {
     return Point::new(self.x - other.x, self.y - other.y, self.z - other.z);
   }
@@ -52,62 +65,117 @@ impl Point {
     return Point::new(self.x + offset.x, self.y + offset.y, self.z + offset.z);
   }
 
-  fn rotate(&self, rotate_index: u8) -> Self {
-    let mut new_point = Point::new(self.x,self.y,self.z);
-    // rotate on axis x and y so that z is facing one of the 6 axes
-    match rotate_index / 4 {
-      0 => {
-          // Unchanged
-      }
-      1 => {
-          // Rotate 180 degrees around the y-axis so z points towards -z
-          new_point = Point::new(-new_point.x, new_point.y, -new_point.z);
-      }
-      2 => {
-          // Rotate 90 degrees around the y-axis so that z points towards -x
-          new_point = Point::new(new_point.z, new_point.y, -new_point.x);
-      }
-      3 => {
-          // Rotate 90 degrees around the y-axis so that z points towards +x
-          new_point = Point::new(-new_point.z, new_point.y, new_point.x);
-      }
-      4 => {
-          // Rotate 90 degrees around the x-axis so that z points towards -y
-          new_point = Point::new(new_point.x, new_point.z, -new_point.y);
-      }
-      5 => {
-          // Rotate 90 degrees around the x-axis so that z points towards +y
-          new_point = Point::new(new_point.x, -new_point.z, new_point.y);
+}
+
+// Rotation by multiple of 90 degrees: a 3x3 matrix with a single 1 or -1 per row and column
+#[derive(Hash, PartialEq, Eq, Clone, Copy, Debug)]
+struct Rotation {
+  matrix: [[i64; 3]; 3],
+}
+
+impl Rotation {
+  fn identity() -> Self {
+    Self {
+      matrix: [[1, 0, 0], [0, 1, 0], [0, 0, 1]],
+    }
+  }
+
+  fn apply(&self, point: &Point) -> Point {
+    let p = point.coordinates();
+    let m = &self.matrix;
+    return Point::new(
+      m[0][0] * p[0] + m[0][1] * p[1] + m[0][2] * p[2],
+      m[1][0] * p[0] + m[1][1] * p[1] + m[1][2] * p[2],
+      m[2][0] * p[0] + m[2][1] * p[1] + m[2][2] * p[2],
+    );
+  }
+
+  // Rotation applying other first, then self
+  fn compose(&self, other: &Rotation) -> Rotation {
+    let mut matrix = [[0; 3]; 3];
+    for (i, row) in matrix.iter_mut().enumerate() {
+      for (j, cell) in row.iter_mut().enumerate() {
+        *cell = (0..3).map(|k|self.matrix[i][k] * other.matrix[k][j]).sum();
       }
-      _ => panic!("Invalid rotate index"),
     }
+    return Rotation { matrix };
+  }
 
-    // rotate on z axis 
-    match rotate_index % 4 {
-      0 => {} // Unchanged
-      1 => {
-          // Rotate 90 degrees around z
-          new_point = Point::new(-new_point.y, new_point.x, new_point.z);
+  // Rotation matrices are orthogonal, the inverse is the transpose
+  fn inverse(&self) -> Rotation {
+    let mut matrix = [[0; 3]; 3];
+    for (i, row) in matrix.iter_mut().enumerate() {
+      for (j, cell) in row.iter_mut().enumerate() {
+        *cell = self.matrix[j][i];
       }
-      2 => {
-          // Rotate 180 degrees around z
-          new_point = Point::new(-new_point.x, -new_point.y, new_point.z);
+    }
+    return Rotation { matrix };
+  }
+
+  fn determinant(&self) -> i64 {
+    let m = &self.matrix;
+    return m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+      - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+      + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0]);
+  }
+}
+
+// The 24 orientations a scanner can have: every axis permutation with every sign
+// combination gives 48 matrices, half of them are mirrors (determinant -1)
+fn rotation_group() -> Vec<Rotation> {
+  let permutations = [[0, 1, 2], [0, 2, 1], [1, 0, 2], [1, 2, 0], [2, 0, 1], [2, 1, 0]];
+  let mut rotations: Vec<Rotation> = Vec::new();
+  for permutation in permutations {
+    for signs in 0..8 {
+      let mut matrix = [[0; 3]; 3];
+      for (row, column) in permutation.iter().enumerate() {
+        matrix[row][*column] = if signs & (1 << row) == 0 { 1 } else { -1 };
       }
-      3 => {
-          // Rotate 270 degrees around z
-          new_point = Point::new(new_point.y, -new_point.x, new_point.z);
+      let rotation = Rotation { matrix };
+      if rotation.determinant() == 1 {
+        rotations.push(rotation);
       }
-      _ => panic!("Invalid rotate index"),
     }
-
-    return new_point;
   }
+  return rotations;
 }
 
-#[derive(Clone)]
-struct RotationAndOffset {
-  rotation_index: u8,
-  offset: Point,
+// Rigid transform: rotate first, then translate
+#[derive(Clone, Debug)]
+struct Transform {
+  rotation: Rotation,
+  translation: Point,
+}
+
+impl Transform {
+  fn identity() -> Self {
+    Self {
+      rotation: Rotation::identity(),
+      translation: Point::new(0, 0, 0),
+    }
+  }
+
+  fn apply(&self, point: &Point) -> Point {
+    return self.rotation.apply(point).add_offset(&self.translation);
+  }
+
+  // Transform applying other first, then self
+  fn compose(&self, other: &Transform) -> Transform {
+    Transform {
+      rotation: self.rotation.compose(&other.rotation),
+      translation: self.apply(&other.translation),
+    }
+  }
+
+  // Undoes the translation, then the rotation
+  fn inverse(&self) -> Transform {
+    let rotation = self.rotation.inverse();
+    let translation = rotation.apply(&self.translation);
+    Transform {
+      rotation,
+      translation: Point::new(-translation.x, -translation.y, -translation.z),
+    }
+  }
 }
 
 #[derive(Clone, Debug)]
@@ -126,56 +194,42 @@ impl Scanner {
     }
   }
 
-  fn rotate(&mut self, rotation_index: u8) {
+  fn transform(&mut self, transform: &Transform) {
     // update beacons
     let mut new_beacons: Vec<Point> = Vec::new();
     for beacon in &self.beacons {
-      new_beacons.push(beacon.rotate(rotation_index));
-    }
-    self.beacons.clear();
-    self.beacons.append(&mut new_beacons);
-  }
-
-
-  fn add_offset(&mut self, offset: &Point) {
-    // update beacons
-    let mut new_beacons: Vec<Point> = Vec::new();
-    for beacon in &self.beacons {
-      new_beacons.push(beacon.add_offset(offset));
+      new_beacons.push(transform.apply(beacon));
     }
     self.beacons = new_beacons;
   }
 
-  fn matches(&self, other: &Scanner) -> Option<Point> {
-    // iterate over other scanners 
-    for my_beacon in self.beacons.clone() {
-      for other_beacon in other.beacons.clone() {
-        let offset = other_beacon.offset_from(&my_beacon);
+  fn matches(&self, other: &Scanner, correspondences: &HashSet<(usize, usize)>, min_overlap: usize) -> Option<Point> {
+    // each candidate correspondence votes for the offset that would bring
+    // my beacon onto the other beacon
+    let mut votes_by_offset: HashMap<Point, usize> = HashMap::new();
+    for (my_index, other_index) in correspondences {
+      let offset = other.beacons[*other_index].offset_from(&self.beacons[*my_index]);
+      *votes_by_offset.entry(offset).or_insert(0) += 1;
+    }
 
-        // count how many points in both scanners
-        let mut count = 0;
-        for a in self.beacons.clone() {
-          if other.beacons.contains(&a.add_offset(&offset)) {
-            count += 1;
-          }
-        }
+    let other_beacons: HashSet<&Point> = other.beacons.iter().collect();
+    for (offset, votes) in votes_by_offset {
+      if votes < min_overlap {
+        continue;
+      }
 
-        if count == 0 {
-          // not possible, at least my beacon and other beacon should match
-          println!("my_beacon {:?}", my_beacon);
-          println!("other_beacon {:?}", other_beacon);
-          println!("offset {:?}", offset);
-          println!("replaced {:?}", my_beacon.add_offset(&offset));
-          panic!("Should not happen")
-        }
+      // count how many points in both scanners
+      let count = self.beacons
+        .iter()
+        .filter(|a|other_beacons.contains(&a.add_offset(&offset)))
+        .count();
 
-        if count >= 12 {
-          // match found
-          return Some(offset);
-        }
+      if count >= min_overlap {
+        // match found
+        return Some(offset);
       }
     }
-    
+
     None
   }
 
@@ -189,6 +243,77 @@ impl Scanner {
   }
 }
 
+// Squared distances between every pair of beacons of a scanner. They don't change
+// with rotations and offsets, so they can be compared before aligning scanners
+struct Fingerprint {
+  distances: Vec<i64>,
+  pairs_by_distance: HashMap<i64, Vec<(usize, usize)>>,
+}
+
+impl Fingerprint {
+  fn from_scanner(scanner: &Scanner) -> Self {
+    let mut distances: Vec<i64> = Vec::new();
+    let mut pairs_by_distance: HashMap<i64, Vec<(usize, usize)>> = HashMap::new();
+    for i in 0..scanner.beacons.len() {
+      for j in i+1..scanner.beacons.len() {
+        let distance = scanner.beacons[i].squared_distance(&scanner.beacons[j]);
+        distances.push(distance);
+        pairs_by_distance.entry(distance).or_default().push((i, j));
+      }
+    }
+    distances.sort();
+    Self {
+      distances,
+      pairs_by_distance,
+    }
+  }
+
+  fn shared_distances(&self, other: &Fingerprint) -> usize {
+    // both lists are sorted, walk them together
+    let mut count = 0;
+    let mut i = 0;
+    let mut j = 0;
+    while i < self.distances.len() && j < other.distances.len() {
+      if self.distances[i] == other.distances[j] {
+        count += 1;
+        i += 1;
+        j += 1;
+      } else if self.distances[i] < other.distances[j] {
+        i += 1;
+      } else {
+        j += 1;
+      }
+    }
+    return count;
+  }
+
+  // Beacon index pairs (mine, other) that could be the same beacon, because they
+  // are both ends of pairs at the same distance. Empty if the scanners cannot overlap
+  fn correspondences(&self, other: &Fingerprint, min_overlap: usize) -> HashSet<(usize, usize)> {
+    let mut correspondences: HashSet<(usize, usize)> = HashSet::new();
+    // N overlapping beacons share at least N*(N-1)/2 distances, min_overlap is at least 3
+    if self.shared_distances(other) < min_overlap * (min_overlap - 1) / 2 {
+      return correspondences;
+    }
+
+    for (distance, my_pairs) in &self.pairs_by_distance {
+      let other_pairs = match other.pairs_by_distance.get(distance) {
+        Some(pairs) => pairs,
+        None => continue,
+      };
+      for (my_a, my_b) in my_pairs {
+        for (other_a, other_b) in other_pairs {
+          correspondences.insert((*my_a, *other_a));
+          correspondences.insert((*my_a, *other_b));
+          correspondences.insert((*my_b, *other_a));
+          correspondences.insert((*my_b, *other_b));
+        }
+      }
+    }
+    return correspondences;
+  }
+}
+
 fn parse_scanners(data: &Vec<String>) -> Vec<Scanner> {
   let mut scanners: Vec<Scanner> = Vec::new();
   let mut scanner_data: Vec<String> = Vec::new();
@@ -216,118 +341,315 @@ fn parse_scanners(data: &Vec<String>) -> Vec<Scanner> {
   return scanners;
 }
 
-fn rotate_all_scanners(scanners: &Vec<Scanner>) -> Vec<Vec<Scanner>> {
-  let mut rotated_scanners: Vec<Vec<Scanner>> = Vec::with_capacity(scanners.len());
+// Every scanner in every orientation, the first one being the identity rotation
+fn rotate_all_scanners(scanners: &Vec<Scanner>) -> Vec<Vec<(Rotation, Scanner)>> {
+  let rotations = rotation_group();
+  let mut rotated_scanners: Vec<Vec<(Rotation, Scanner)>> = Vec::with_capacity(scanners.len());
   for scanner in scanners {
-    let mut rotated_scanner_i: Vec<Scanner> = Vec::with_capacity(24);
-    for rotation_index in 0..24 {
+    let mut rotated_scanner_i: Vec<(Rotation, Scanner)> = Vec::with_capacity(rotations.len());
+    for rotation in &rotations {
       let mut scanner_rotated = scanner.clone();
-      scanner_rotated.rotate(rotation_index);
-      rotated_scanner_i.push(scanner_rotated);
+      scanner_rotated.transform(&Transform { rotation: *rotation, translation: Point::new(0, 0, 0) });
+      rotated_scanner_i.push((*rotation, scanner_rotated));
     }
     rotated_scanners.push(rotated_scanner_i);
   }
   return rotated_scanners;
 }
 
-fn align_scanners(scanners: &Vec<Scanner>) -> (HashMap<usize, Point>, Scanner) {
-  let all_rotated_scanners = rotate_all_scanners(scanners);
-  let mut global_scanner: Scanner = scanners[0].clone();
-  let mut aligned : HashMap<usize, Scanner> = HashMap::new();
-  let mut offsets: HashMap<usize, Point> = HashMap::new();
+struct AlignmentReport {
+  // poses and beacons of the scanners connected to scanner 0, in its coordinates
+  poses: HashMap<usize, Transform>,
+  global_scanner: Scanner,
+  // groups of scanners that can be aligned together, the first one contains scanner 0
+  components: Vec<Vec<usize>>,
+  unaligned: Vec<usize>,
+}
+
+impl AlignmentReport {
+  fn is_complete(&self) -> bool {
+    self.unaligned.is_empty()
+  }
+}
+
+impl fmt::Display for AlignmentReport {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    writeln!(f, "{} scanners aligned with scanner 0, {} unaligned", self.poses.len(), self.unaligned.len())?;
+    for (index, component) in self.components.iter().enumerate() {
+      writeln!(f, "Component {}: scanners {:?}", index, component)?;
+    }
+    if !self.is_complete() {
+      writeln!(f, "Unaligned scanners: {:?}", self.unaligned)?;
+    }
+    Ok(())
+  }
+}
+
+impl AlignmentReport {
+  // The beacons map in the coordinates of an aligned scanner
+  fn local_map(&self, index: usize) -> Option<Scanner> {
+    let pose = self.poses.get(&index)?;
+    let mut map = self.global_scanner.clone();
+    map.transform(&pose.inverse());
+    Some(map)
+  }
+
+  fn sorted_scanners(&self) -> Vec<(&usize, &Transform)> {
+    let mut scanners: Vec<(&usize, &Transform)> = self.poses.iter().collect();
+    scanners.sort_by_key(|(index, _)|**index);
+    return scanners;
+  }
+
+  fn beacons_to_csv(&self) -> String {
+    let mut csv = "x,y,z\n".to_owned();
+    for beacon in &self.global_scanner.beacons {
+      csv += &format!("{},{},{}\n", beacon.x, beacon.y, beacon.z);
+    }
+    return csv;
+  }
 
-  aligned.insert(0, scanners[0].clone());
-  offsets.insert(0, Point::new(0, 0, 0));
+  // Orientation is the rotation matrix taking the scanner axes to scanner 0 axes
+  fn scanners_to_csv(&self) -> String {
+    let mut csv = "scanner,x,y,z,r11,r12,r13,r21,r22,r23,r31,r32,r33\n".to_owned();
+    for (index, pose) in self.sorted_scanners() {
+      let position = &pose.translation;
+      let mut row = format!("{},{},{},{}", index, position.x, position.y, position.z);
+      for matrix_row in pose.rotation.matrix {
+        for cell in matrix_row {
+          row += &format!(",{}", cell);
+        }
+      }
+      csv += &row;
+      csv += "\n";
+    }
+    return csv;
+  }
 
-  while aligned.len() < scanners.len() {
-    let mut match_found = false;
-    for i in 1..scanners.len() {
-      if aligned.contains_key(&i) {
+  // ASCII PLY point cloud, beacons in white and scanners in red
+  fn to_ply(&self) -> String {
+    let scanners = self.sorted_scanners();
+    let mut ply = "ply\nformat ascii 1.0\ncomment day19 beacons and scanners\n".to_owned();
+    ply += &format!("element vertex {}\n", self.global_scanner.beacons.len() + scanners.len());
+    for property in ["int x", "int y", "int z", "uchar red", "uchar green", "uchar blue"] {
+      ply += &format!("property {}\n", property);
+    }
+    ply += "end_header\n";
+    for beacon in &self.global_scanner.beacons {
+      ply += &format!("{} {} {} 255 255 255\n", beacon.x, beacon.y, beacon.z);
+    }
+    for (_, pose) in scanners {
+      ply += &format!("{} {} {} 255 0 0\n", pose.translation.x, pose.translation.y, pose.translation.z);
+    }
+    return ply;
+  }
+
+  // Orthographic projection looking along drop_axis (0 = x, 1 = y, 2 = z),
+  // beacons are drawn as # and scanners as S
+  fn draw_projection(&self, drop_axis: usize, width: usize, height: usize) -> String {
+    let axes: Vec<usize> = (0..3).filter(|axis|*axis != drop_axis).collect();
+    let beacons: Vec<[i64; 3]> = self.global_scanner.beacons.iter().map(|b|b.coordinates()).collect();
+    let scanners: Vec<[i64; 3]> = self.sorted_scanners().iter().map(|(_, pose)|pose.translation.coordinates()).collect();
+
+    let all_points = beacons.iter().chain(scanners.iter());
+    let min_h = all_points.clone().map(|p|p[axes[0]]).min().unwrap();
+    let max_h = all_points.clone().map(|p|p[axes[0]]).max().unwrap();
+    let min_v = all_points.clone().map(|p|p[axes[1]]).min().unwrap();
+    let max_v = all_points.map(|p|p[axes[1]]).max().unwrap();
+
+    let to_cell = |p: &[i64; 3]| -> (usize, usize) {
+      let column = (p[axes[0]] - min_h) * (width as i64 - 1) / i64::max(max_h - min_h, 1);
+      // vertical axis grows upwards
+      let row = (max_v - p[axes[1]]) * (height as i64 - 1) / i64::max(max_v - min_v, 1);
+      (row as usize, column as usize)
+    };
+
+    let mut grid: Vec<Vec<char>> = vec![vec!['.'; width]; height];
+    for beacon in &beacons {
+      let (row, column) = to_cell(beacon);
+      grid[row][column] = '#';
+    }
+    for scanner in &scanners {
+      let (row, column) = to_cell(scanner);
+      grid[row][column] = 'S';
+    }
+
+    return grid
+      .iter()
+      .map(|row|row.iter().collect::<String>())
+      .collect::<Vec<String>>()
+      .join("\n");
+  }
+}
+
+// Aligns every scanner reachable from root among candidates, poses are relative to root.
+// Stops when no aligned scanner is left to compare with the remaining ones
+fn align_component(
+  root: usize,
+  candidates: &HashSet<usize>,
+  all_rotated_scanners: &Vec<Vec<(Rotation, Scanner)>>,
+  fingerprints: &Vec<Fingerprint>,
+  min_overlap: usize,
+) -> (HashMap<usize, Transform>, Scanner) {
+  let mut global_scanner: Scanner = all_rotated_scanners[root][0].1.clone();
+  let mut poses: HashMap<usize, Transform> = HashMap::new();
+
+  poses.insert(root, Transform::identity());
+
+  // aligned scanners not yet used to align others
+  let mut to_visit: Vec<usize> = vec![root];
+
+  while let Some(j) = to_visit.pop() {
+    // match against scanner j in its own coordinates, then chain with its pose
+    let scanner_j = &all_rotated_scanners[j][0].1;
+    let pose_j = poses.get(&j).unwrap().clone();
+    let mut remaining: Vec<usize> = candidates
+      .iter()
+      .filter(|i|!poses.contains_key(i))
+      .cloned()
+      .collect();
+    remaining.sort();
+    for i in remaining {
+      // beacons keep their index when rotated and offset, so correspondences
+      // found on the original scanners hold for the rotated ones
+      let correspondences = fingerprints[i].correspondences(&fingerprints[j], min_overlap);
+      if correspondences.is_empty() {
         continue;
       }
 
-      // try aligning scanner i with aligned scanners
-      // iterate over aligned scanners 
-      for (j, scanner_aligned) in aligned.clone().iter() {
-        if i == *j {
-          panic!("i and j should not be the same");
-        }
-        for rotated_scanner in all_rotated_scanners.get(i).unwrap() {
-          let res = rotated_scanner.matches(&scanner_aligned);
-          if res.is_none() {
-            continue
-          }
-          let offset = res.unwrap();
-          println!("Scanner {} matched with scanner {}, offset {:?}", i, j, offset);
-
-          let mut aligned_scanner = rotated_scanner.clone();
-          aligned_scanner.add_offset(&offset);
-          global_scanner.add_beacons(&aligned_scanner.clone().beacons);
-
-          println!("Offset to 0 {:?}", offset);
-          
-          offsets.insert(i, offset);
-
-          aligned.insert(i, aligned_scanner.clone());
-          match_found = true;
-          break;
+      for (rotation, rotated_scanner) in all_rotated_scanners.get(i).unwrap() {
+        let res = rotated_scanner.matches(scanner_j, &correspondences, min_overlap);
+        if res.is_none() {
+          continue
         }
+        let offset = res.unwrap();
+        println!("Scanner {} matched with scanner {}, offset {:?}", i, j, offset);
 
-        if match_found {
-          break;
-        }
-      }
+        let pose_i = pose_j.compose(&Transform { rotation: *rotation, translation: offset });
+        let mut aligned_scanner = all_rotated_scanners[i][0].1.clone();
+        aligned_scanner.transform(&pose_i);
+        global_scanner.add_beacons(&aligned_scanner.beacons);
 
-      if match_found {
+        poses.insert(i, pose_i);
+        to_visit.push(i);
         break;
       }
     }
-    if !match_found {
-      for i in 1..scanners.len() {
-        if aligned.contains_key(&i) {
-          continue;
-        }
-        println!("No match for scanner {}", i);
-      }
-      panic!("Oh no");
+  }
+
+  return (poses, global_scanner);
+}
+
+fn align_scanners(scanners: &Vec<Scanner>, min_overlap: usize) -> Result<AlignmentReport, String> {
+  // fewer than 3 shared beacons can't fix the orientation of a scanner
+  if min_overlap < 3 {
+    return Err(format!("Minimum overlap {} is too small, at least 3 beacons must match", min_overlap));
+  }
+  let all_rotated_scanners = rotate_all_scanners(scanners);
+  let fingerprints: Vec<Fingerprint> = scanners.iter().map(Fingerprint::from_scanner).collect();
+  let mut candidates: HashSet<usize> = (0..scanners.len()).collect();
+
+  let (poses, global_scanner) = align_component(0, &candidates, &all_rotated_scanners, &fingerprints, min_overlap);
+  let mut components: Vec<Vec<usize>> = Vec::new();
+  let mut component: Vec<usize> = poses.keys().cloned().collect();
+  component.sort();
+  for i in &component {
+    candidates.remove(i);
+  }
+  components.push(component);
+
+  let mut unaligned: Vec<usize> = candidates.iter().cloned().collect();
+  unaligned.sort();
+
+  // group the scanners left behind, to tell apart a single lost scanner from a split map
+  while let Some(root) = candidates.iter().min().cloned() {
+    let (component_poses, _) = align_component(root, &candidates, &all_rotated_scanners, &fingerprints, min_overlap);
+    let mut component: Vec<usize> = component_poses.keys().cloned().collect();
+    component.sort();
+    for i in &component {
+      candidates.remove(i);
     }
+    components.push(component);
   }
 
-  return (offsets, global_scanner);
+  return Ok(AlignmentReport {
+    poses,
+    global_scanner,
+    components,
+    unaligned,
+  });
 }
 
-fn solution_1(data: &Vec<String>) -> usize {
+fn solution_1(data: &Vec<String>) -> Result<usize, String> {
   let scanners = parse_scanners(data);
 
-  let (_, global_scanner) = align_scanners(&scanners);
+  let report = align_scanners(&scanners, MIN_OVERLAP)?;
+  if !report.is_complete() {
+    println!("{}", report);
+  }
 
-  return global_scanner.beacons.len();
-  442
+  return Ok(report.global_scanner.beacons.len());
 }
 
-fn solution_2(data: &Vec<String>) -> i64 {
+fn solution_2(data: &Vec<String>) -> Result<i64, String> {
   let scanners = parse_scanners(data);
 
-  let (offsets, _) = align_scanners(&scanners);
+  let report = align_scanners(&scanners, MIN_OVERLAP)?;
+  if !report.is_complete() {
+    println!("{}", report);
+  }
 
+  let offsets: Vec<&Point> = report.poses.values().map(|pose|&pose.translation).collect();
   let mut max_distance = i64::MIN;
 
   for i in 0..offsets.len()-1 {
     for j in i+1..offsets.len() {
-      let manhattan_distance = offsets.get(&i).unwrap().manhattan_distance(&offsets.get(&j).unwrap());
+      let manhattan_distance = offsets[i].manhattan_distance(offsets[j]);
       max_distance = i64::max(max_distance, manhattan_distance);
     }
   }
 
-  return max_distance;
+  return Ok(max_distance);
+}
+
+fn export_map(data: &Vec<String>, output_path: &str) {
+  let scanners = parse_scanners(data);
+
+  let report = match align_scanners(&scanners, MIN_OVERLAP) {
+    Ok(report) => report,
+    Err(e) => {
+      println!("❌ Alignment failed: {}", e);
+      return;
+    }
+  };
+  println!("{}", report);
+
+  fs::create_dir_all("outputs").unwrap();
+  fs::write(format!("{}.beacons.csv", output_path), report.beacons_to_csv()).unwrap();
+  fs::write(format!("{}.scanners.csv", output_path), report.scanners_to_csv()).unwrap();
+  fs::write(format!("{}.ply", output_path), report.to_ply()).unwrap();
+
+  // brought back to each scanner coordinates, the map must hold what the scanner saw
+  for (index, _) in report.sorted_scanners() {
+    let local_map: HashSet<Point> = report.local_map(*index).unwrap().beacons.into_iter().collect();
+    let missing = scanners[*index].beacons.iter().filter(|b|!local_map.contains(b)).count();
+    if missing > 0 {
+      println!("Scanner {}: {} beacons missing from the map", index, missing);
+    }
+  }
+
+  println!("====== Top view (x, y) ========");
+  println!("{}", report.draw_projection(2, 80, 40));
 }
 
 fn main() {
   let test = false;
+  let export = false;
   let mut file_path: String = "inputs/day19".to_string();
   let mut emoji: String = "🎉".to_string();
+  let mut output_path: String = "outputs/day19".to_string();
   if test {
     file_path += ".test";
+    output_path += ".test";
     emoji = "🧪".to_string();
   }
   file_path += ".txt";
@@ -338,7 +660,146 @@ fn main() {
               .map(|line|line.unwrap())
               .collect();
 
-  println!(r#"{:?} Part 1 result is {:?}"#, emoji, solution_1(&data));
+  match solution_1(&data) {
+    Ok(result) => println!(r#"{:?} Part 1 result is {:?}"#, emoji, result),
+    Err(e) => println!("❌ Part 1 failed: {}", e),
+  }
+
+  match solution_2(&data) {
+    Ok(result) => println!(r#"{:?} Part 2 test result is {:?}"#, emoji, result),
+    Err(e) => println!("❌ Part 2 failed: {}", e),
+  }
+
+  if export {
+    export_map(&data, &output_path);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn rotation_group_has_24_distinct_rotations() {
+    let rotations = rotation_group();
+    let set: HashSet<Rotation> = rotations.iter().copied().collect();
+    assert_eq!(rotations.len(), 24);
+    assert_eq!(set.len(), 24);
+    assert_eq!(rotations[0], Rotation::identity());
+  }
+
+  #[test]
+  fn rotations_have_determinant_1() {
+    assert!(rotation_group().iter().all(|r|r.determinant() == 1));
+  }
+
+  #[test]
+  fn rotation_group_is_closed_under_composition() {
+    let rotations = rotation_group();
+    let set: HashSet<Rotation> = rotations.iter().copied().collect();
+    for a in &rotations {
+      for b in &rotations {
+        assert!(set.contains(&a.compose(b)), "{:?} * {:?} is not a rotation", a, b);
+      }
+    }
+  }
+
+  #[test]
+  fn rotation_inverse_is_in_group_and_undoes_it() {
+    let rotations = rotation_group();
+    let set: HashSet<Rotation> = rotations.iter().copied().collect();
+    let p = Point::new(1, -2, 3);
+    for r in &rotations {
+      let inverse = r.inverse();
+      assert!(set.contains(&inverse));
+      assert_eq!(r.compose(&inverse), Rotation::identity());
+      assert_eq!(inverse.compose(r), Rotation::identity());
+      assert_eq!(inverse.apply(&r.apply(&p)), p);
+    }
+  }
+
+  #[test]
+  fn transform_compose_applies_other_first() {
+    let rotations = rotation_group();
+    let p = Point::new(5, -7, 11);
+    for (i, a) in rotations.iter().enumerate() {
+      let b = &rotations[(i * 7 + 3) % rotations.len()];
+      let first = Transform { rotation: *b, translation: Point::new(-3, 4, 10) };
+      let second = Transform { rotation: *a, translation: Point::new(100, -20, 2) };
+      assert_eq!(second.compose(&first).apply(&p), second.apply(&first.apply(&p)));
+    }
+
+    // rotate x onto y, then shift
+    let quarter_turn = Rotation { matrix: [[0, -1, 0], [1, 0, 0], [0, 0, 1]] };
+    let t = Transform { rotation: quarter_turn, translation: Point::new(10, 0, 0) };
+    assert_eq!(t.apply(&Point::new(1, 0, 0)), Point::new(10, 1, 0));
+    assert_eq!(t.compose(&t).apply(&Point::new(1, 0, 0)), Point::new(9, 10, 0));
+    assert_eq!(Transform::identity().compose(&t).apply(&p), t.apply(&p));
+  }
+
+  fn test_scanners() -> Vec<Scanner> {
+    let data: Vec<String> = fs::read_to_string("inputs/day19.test.txt")
+      .unwrap()
+      .lines()
+      .map(|line|line.to_string())
+      .collect();
+    parse_scanners(&data)
+  }
+
+  #[test]
+  fn aligns_every_test_scanner() {
+    let report = align_scanners(&test_scanners(), MIN_OVERLAP).unwrap();
+    assert!(report.is_complete());
+    assert_eq!(report.components, vec![vec![0, 1, 2, 3, 4]]);
+    assert_eq!(report.global_scanner.beacons.len(), 79);
+  }
+
+  // Beacons far from everything the other scanners saw
+  fn cut_off(scanners: &mut Vec<Scanner>, index: usize) {
+    scanners[index].beacons = (0..26).map(|i|Point::new(5000 + i * i, 7000 - 3 * i, 9000 + i * i * i)).collect();
+  }
 
-  println!(r#"{:?} Part 2 test result is {:?}"#, emoji, solution_2(&data));
+  #[test]
+  fn reports_a_scanner_cut_off_from_the_others() {
+    // scanner 2 only overlaps scanner 4
+    let mut scanners = test_scanners();
+    cut_off(&mut scanners, 2);
+
+    let report = align_scanners(&scanners, MIN_OVERLAP).unwrap();
+    assert!(!report.is_complete());
+    assert_eq!(report.unaligned, vec![2]);
+    assert_eq!(report.components, vec![vec![0, 1, 3, 4], vec![2]]);
+    let mut aligned: Vec<usize> = report.poses.keys().copied().collect();
+    aligned.sort();
+    assert_eq!(aligned, vec![0, 1, 3, 4]);
+  }
+
+  #[test]
+  fn reports_scanners_left_behind_by_a_cut() {
+    // scanner 4 links scanner 2 to the others
+    let mut scanners = test_scanners();
+    cut_off(&mut scanners, 4);
+
+    let report = align_scanners(&scanners, MIN_OVERLAP).unwrap();
+    assert_eq!(report.unaligned, vec![2, 4]);
+    assert_eq!(report.components, vec![vec![0, 1, 3], vec![2], vec![4]]);
+  }
+
+  #[test]
+  fn rejects_overlaps_below_3() {
+    for min_overlap in 0..3 {
+      assert!(align_scanners(&test_scanners(), min_overlap).is_err());
+    }
+  }
+
+  #[test]
+  fn transform_inverse_undoes_it() {
+    let p = Point::new(5, -7, 11);
+    for (i, rotation) in rotation_group().iter().enumerate() {
+      let t = Transform { rotation: *rotation, translation: Point::new(i as i64, -3, 40) };
+      assert_eq!(t.inverse().apply(&t.apply(&p)), p);
+      assert_eq!(t.apply(&t.inverse().apply(&p)), p);
+      assert_eq!(t.inverse().compose(&t).apply(&p), p);
+    }
+  }
 }
@@ -1,7 +1,40 @@
 use std::collections::HashMap;
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::{self, BufRead};
 
+#[derive(Debug, Clone)]
+struct GameRules {
+  board_size: i64,
+  winning_score: i64,
+  rolls_per_turn: u32,
+  die_sides: i64,
+}
+
+impl GameRules {
+  fn deterministic() -> Self {
+    Self {
+      board_size: 10,
+      winning_score: 1000,
+      rolls_per_turn: 3,
+      die_sides: 100,
+    }
+  }
+
+  fn dirac() -> Self {
+    Self {
+      board_size: 10,
+      winning_score: 21,
+      rolls_per_turn: 3,
+      die_sides: 3,
+    }
+  }
+
+  // spaces are numbered 1 to board_size, moving past the last one wraps around
+  fn move_position(&self, position: i64, steps: i64) -> i64 {
+    (position - 1 + steps).rem_euclid(self.board_size) + 1
+  }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 struct Player {
   position: i64,
@@ -11,40 +44,24 @@ struct Player {
 impl Player {
   fn parse(data: &String) -> Self {
     Self {
-      position: data.chars().last().unwrap().to_digit(10).unwrap() as i64,
+      position: data.split(": ").last().unwrap().parse().unwrap(),
       points: 0
     }
   }
 
-  fn play(&mut self, dice: &mut Dice) {
-    let mut sum = 0;
-    let mut sums: Vec<String> = Vec::with_capacity(3);
-    for _ in 0..3{
-      sum += dice.roll();
-      sums.push(dice.value.to_string());
-    }
-    println!("{:?}", sums.join("+"));
-    self.position += sum;
-    if self.position > 10 {
-      self.position %= 10;
-      if self.position == 0 {
-        self.position = 10;
-      } 
-    }
+  // Returns the rolled values
+  fn play(&mut self, dice: &mut Dice, rules: &GameRules) -> Vec<i64> {
+    let rolls: Vec<i64> = (0..rules.rolls_per_turn).map(|_|dice.roll()).collect();
+    self.position = rules.move_position(self.position, rolls.iter().sum());
     self.points += self.position;
+    return rolls;
   }
 
-  fn play_quantum(&self, dice: &QuantumDice) -> HashMap<Player, u64> {
-    let mut quantum_players: HashMap<Player, u64> = HashMap::new();
+  fn play_quantum(&self, dice: &QuantumDice, rules: &GameRules) -> HashMap<Player, u128> {
+    let mut quantum_players: HashMap<Player, u128> = HashMap::new();
 
-    for (dice_sum, count) in dice.values.clone() {
-      let mut position = self.position + dice_sum;
-      if position > 10 {
-        position %= 10;
-        if position == 0 {
-          position = 10;
-        } 
-      }
+    for (dice_sum, count) in &dice.values {
+      let position = rules.move_position(self.position, *dice_sum);
       let points = self.points + position;
       let player = Player{
         points,
@@ -57,19 +74,21 @@ impl Player {
 }
 
 struct QuantumDice {
-  values: HashMap<i64, u64>,
+  // how many universes roll each sum in one turn
+  values: HashMap<i64, u128>,
 }
 
 impl QuantumDice {
-  fn new() -> Self {
-    let mut values: HashMap<i64, u64> = HashMap::new();
-    for i in 0..3 {
-      for j in 0..3 {
-        for v in 0..3 {
-          let sum = i + 1 + j + 1 + v + 1;
-          *values.entry(sum).or_insert(0) += 1;
+  fn new(rules: &GameRules) -> Self {
+    let mut values: HashMap<i64, u128> = HashMap::from([(0, 1)]);
+    for _ in 0..rules.rolls_per_turn {
+      let mut new_values: HashMap<i64, u128> = HashMap::new();
+      for (sum, count) in &values {
+        for face in 1..=rules.die_sides {
+          *new_values.entry(sum + face).or_insert(0) += count;
         }
       }
+      values = new_values;
     }
     Self {
       values
@@ -77,129 +96,201 @@ impl QuantumDice {
   }
 }
 
-#[derive(Debug)]
+// Deterministic die rolling first_roll, first_roll + 1, ... up to sides, then 1 again
+#[derive(Debug, Clone)]
 struct Dice {
   value: i64,
+  sides: i64,
   tot_rolls: i64,
 }
 
 impl Dice {
-  fn new() -> Self {
+  fn new(sides: i64, first_roll: i64) -> Self {
+    if first_roll < 1 || first_roll > sides {
+      panic!("First roll {} is not a face of a {} sided die", first_roll, sides);
+    }
     Self {
-      value: 0,
+      // last rolled value
+      value: first_roll - 1,
+      sides,
       tot_rolls: 0,
     }
   }
 
   fn roll(&mut self) -> i64 {
-    self.value += 1;
-    if self.value > 100 {
-      self.value -= 100;
-    }
+    self.value = self.value % self.sides + 1;
     self.tot_rolls += 1;
     return self.value;
   }
 }
 
-#[derive(Clone, PartialEq, Eq, Hash)]
-struct Universe {
-  player_1: Player,
-  player_2: Player,
+#[derive(Debug, Clone)]
+struct TurnEvent {
+  turn: usize,
+  player: usize,
+  rolls: Vec<i64>,
+  position: i64,
+  score: i64,
 }
 
-fn solution_1(data: &Vec<String>) -> i64 {
-  let mut players: Vec<Player> = Vec::with_capacity(2);
-
-  for d in data {
-    players.push(Player::parse(d));
-  }
+struct GameLog {
+  events: Vec<TurnEvent>,
+  players: Vec<Player>,
+  winner: usize,
+  tot_rolls: i64,
+}
 
-  for p in &players {
-    println!("{:?}", p);
+impl GameLog {
+  fn to_csv(&self) -> String {
+    let mut csv = "turn,player,rolls,position,score\n".to_owned();
+    for event in &self.events {
+      let rolls: Vec<String> = event.rolls.iter().map(|r|r.to_string()).collect();
+      csv += &format!("{},{},{},{},{}\n", event.turn, event.player + 1, rolls.join("+"), event.position, event.score);
+    }
+    return csv;
   }
+}
 
-  let mut dice = Dice::new();
+fn play_deterministic(players: &Vec<Player>, dice: &mut Dice, rules: &GameRules) -> GameLog {
+  let mut players = players.clone();
+  let mut events: Vec<TurnEvent> = Vec::new();
+  let mut turn = 0;
 
   loop {
     for i in 0..players.len() {
-      let player = players.get_mut(i).unwrap();
-      player.play(&mut dice);
-      println!("Player {}: {:?}", i + 1, player);
-      if player.points >= 1000 {
-        println!("Winner !");
-        for p in &players {
-          println!("{:?}", p);
-        }
-        println!("{:?}", dice);
-        return players.iter().filter(|&p|p.points < 1000).next().unwrap().points * dice.tot_rolls;
+      turn += 1;
+      let rolls = players[i].play(dice, rules);
+      events.push(TurnEvent {
+        turn,
+        player: i,
+        rolls,
+        position: players[i].position,
+        score: players[i].points,
+      });
+      if players[i].points >= rules.winning_score {
+        return GameLog {
+          events,
+          players,
+          winner: i,
+          tot_rolls: dice.tot_rolls,
+        };
       }
     }
   }
 }
 
-fn solution_2(data: &Vec<String>) -> u64 {
-  let mut players: Vec<Player> = Vec::with_capacity(2);
-
-  for d in data {
-    players.push(Player::parse(d));
+// Universes won by each player from this state, player `turn` playing next
+fn count_wins(
+  players: &Vec<Player>,
+  turn: usize,
+  dice: &QuantumDice,
+  rules: &GameRules,
+  wins_by_state: &mut HashMap<(Vec<Player>, usize), Vec<u128>>,
+) -> Vec<u128> {
+  let state = (players.clone(), turn);
+  if let Some(wins) = wins_by_state.get(&state) {
+    return wins.clone();
   }
 
-  for p in &players {
-    println!("{:?}", p);
+  let mut wins: Vec<u128> = vec![0; players.len()];
+  for (player, count) in players[turn].play_quantum(dice, rules) {
+    if player.points >= rules.winning_score {
+      // winner, universe is over
+      wins[turn] += count;
+      continue;
+    }
+    let mut next_players = players.clone();
+    next_players[turn] = player;
+    let next_wins = count_wins(&next_players, (turn + 1) % players.len(), dice, rules, wins_by_state);
+    for (i, w) in next_wins.iter().enumerate() {
+      wins[i] += count * w;
+    }
   }
 
-  let quantum_dice = QuantumDice::new();
-  let mut winner_count: Vec<u64> = vec![0;2];
+  wins_by_state.insert(state, wins.clone());
+  return wins;
+}
 
-  let mut universes: HashMap<Universe, u64> = HashMap::new();
+// Universes won by each player for every pair of starting positions, indexed by
+// player 1 then player 2 starting position (0 being space 1)
+fn win_table(rules: &GameRules) -> Vec<Vec<Vec<u128>>> {
+  let quantum_dice = QuantumDice::new(rules);
+  // states don't depend on where the game started, share them between games
+  let mut wins_by_state: HashMap<(Vec<Player>, usize), Vec<u128>> = HashMap::new();
+  let mut table: Vec<Vec<Vec<u128>>> = Vec::new();
+  for position_1 in 1..=rules.board_size {
+    let mut row: Vec<Vec<u128>> = Vec::new();
+    for position_2 in 1..=rules.board_size {
+      let players = vec![
+        Player { position: position_1, points: 0 },
+        Player { position: position_2, points: 0 },
+      ];
+      row.push(count_wins(&players, 0, &quantum_dice, rules, &mut wins_by_state));
+    }
+    table.push(row);
+  }
+  return table;
+}
 
-  universes.insert(Universe{player_1: players[0].clone(), player_2: players[1].clone()}, 1);
+fn win_probability(wins: &Vec<u128>, player: usize) -> f64 {
+  wins[player] as f64 / wins.iter().sum::<u128>() as f64
+}
 
-  while !universes.is_empty() {
-    // play player 1
-    let mut new_universes: HashMap<Universe, u64> = HashMap::new();
-    for (universe, count) in universes.clone() {
-      let player = universe.player_1;
-      let parallel_players = player.play_quantum(&quantum_dice);
-      for (p, p_count) in parallel_players {
-        if p.points >= 21 {
-          // winner, universe is over
-          winner_count[0] += p_count*count;
-          continue;
-        }
-        let uni = Universe {
-          player_1: p.clone(),
-          player_2: universe.player_2.clone() 
-        };
-        *new_universes.entry(uni).or_insert(0) += p_count * count;
-      }
-    } 
-
-    // play player 2
-    universes.clear();
-    for (universe, count) in new_universes {
-      let player = universe.player_2.clone();
-      let parallel_players = player.play_quantum(&quantum_dice);
-      for (p, p_count) in parallel_players {
-        if p.points >= 21 {
-          // winner, universe is over
-          winner_count[1] += p_count*count;
-          continue;
-        }
-        let uni = Universe {
-          player_2: p.clone(),
-          player_1: universe.player_1.clone() 
-        };
-        *universes.entry(uni).or_insert(0) += p_count * count;
+fn print_win_table(table: &Vec<Vec<Vec<u128>>>, rules: &GameRules) {
+  println!("Player 1 win probability to {} points, player 1 start by row, player 2 start by column", rules.winning_score);
+  let header: Vec<String> = (1..=table.len()).map(|p|format!("{:>5}", p)).collect();
+  println!("    {}", header.join(" "));
+  for (i, row) in table.iter().enumerate() {
+    let cells: Vec<String> = row.iter().map(|wins|format!("{:>5.3}", win_probability(wins, 0))).collect();
+    println!("{:>3} {}", i + 1, cells.join(" "));
+  }
+}
+
+fn win_table_to_csv(tables: &Vec<(GameRules, Vec<Vec<Vec<u128>>>)>) -> String {
+  let mut csv = "winning_score,player_1_start,player_2_start,player_1_wins,player_2_wins,player_1_probability\n".to_owned();
+  for (rules, table) in tables {
+    for (i, row) in table.iter().enumerate() {
+      for (j, wins) in row.iter().enumerate() {
+        csv += &format!("{},{},{},{},{},{}\n", rules.winning_score, i + 1, j + 1, wins[0], wins[1], win_probability(wins, 0));
       }
     }
   }
-  winner_count.sort();
-  return winner_count.last().unwrap().clone();
+  return csv;
+}
+
+fn parse_players(data: &Vec<String>) -> Vec<Player> {
+  data.iter().filter(|d|!d.is_empty()).map(Player::parse).collect()
+}
+
+fn solution_1(data: &Vec<String>, rules: &GameRules, dice: &Dice) -> i64 {
+  let players = parse_players(data);
+  let log = play_deterministic(&players, &mut dice.clone(), rules);
+
+  let losing_points = log.players
+    .iter()
+    .enumerate()
+    .filter(|(i, _)|*i != log.winner)
+    .map(|(_, p)|p.points)
+    .min()
+    .unwrap();
+  return losing_points * log.tot_rolls;
+}
+
+fn solution_2(data: &Vec<String>, rules: &GameRules) -> u128 {
+  let players = parse_players(data);
+
+  let quantum_dice = QuantumDice::new(rules);
+  let winner_count = count_wins(&players, 0, &quantum_dice, rules, &mut HashMap::new());
+
+  return *winner_count.iter().max().unwrap();
 }
 
 fn main() {
   let test = false;
+  let export_log = false;
+  let win_table_scores: Vec<i64> = Vec::new();
+  let deterministic_rules = GameRules::deterministic();
+  let deterministic_dice = Dice::new(deterministic_rules.die_sides, 1);
   let mut file_path: String = "inputs/day21".to_string();
   let mut emoji: String = "🎉".to_string();
   if test {
@@ -214,7 +305,26 @@ fn main() {
               .map(|line|line.unwrap())
               .collect();
 
-  println!(r#"{:?} Part 1 result is {:?}"#, emoji, solution_1(&data));
+  println!(r#"{:?} Part 1 result is {:?}"#, emoji, solution_1(&data, &deterministic_rules, &deterministic_dice));
 
-  println!(r#"{:?} Part 2 test result is {:?}"#, emoji, solution_2(&data));
+  println!(r#"{:?} Part 2 test result is {:?}"#, emoji, solution_2(&data, &GameRules::dirac()));
+
+  if export_log {
+    let log = play_deterministic(&parse_players(&data), &mut deterministic_dice.clone(), &deterministic_rules);
+    fs::create_dir_all("outputs").unwrap();
+    fs::write("outputs/day21.log.csv", log.to_csv()).unwrap();
+    println!("Player {} won after {} turns", log.winner + 1, log.events.len());
+  }
+
+  if !win_table_scores.is_empty() {
+    let mut tables: Vec<(GameRules, Vec<Vec<Vec<u128>>>)> = Vec::new();
+    for winning_score in &win_table_scores {
+      let rules = GameRules { winning_score: *winning_score, ..GameRules::dirac() };
+      let table = win_table(&rules);
+      print_win_table(&table, &rules);
+      tables.push((rules, table));
+    }
+    fs::create_dir_all("outputs").unwrap();
+    fs::write("outputs/day21.win_table.csv", win_table_to_csv(&tables)).unwrap();
+  }
 }
@@ -1,5 +1,10 @@
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::{self, BufRead};
+use std::time::Instant;
+
+mod day22_hyperbox;
+use day22_hyperbox::{BoxSet, HyperBox};
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 struct Point {
@@ -24,6 +29,10 @@ impl Range {
   fn is_in_range(&self, other: &Range) -> bool {
     self.min <= other.max && self.max >= other.min
   }
+
+  fn contains(&self, value: i64) -> bool {
+    self.min <= value && value <= self.max
+  }
 }
 
 #[derive(Debug, Clone)]
@@ -89,17 +98,30 @@ struct Cuboid {
 }
 
 impl Cuboid {
+  fn from_bounds(bounds: &[i64; 6]) -> Self {
+    Self {
+      x: Range::new(bounds[0], bounds[1]),
+      y: Range::new(bounds[2], bounds[3]),
+      z: Range::new(bounds[4], bounds[5]),
+      off: Vec::new()
+    }
+  }
+
+  fn bounds(&self) -> [i64; 6] {
+    [self.x.min, self.x.max, self.y.min, self.y.max, self.z.min, self.z.max]
+  }
+
   fn intersects(&self, other: &Cuboid) -> bool {
     self.x.is_in_range(&other.x) &&
     self.y.is_in_range(&other.y) &&
     self.z.is_in_range(&other.z)
   }
 
-  fn subtract(&mut self, other: &Cuboid) {
+  fn intersection(&self, other: &Cuboid) -> Option<Cuboid> {
     if !self.intersects(other) {
-      return;
+      return None;
     }
-    let intersect_cube = Cuboid {
+    Some(Cuboid {
       x: Range{
         min: i64::max(self.x.min, other.x.min),
         max: i64::min(self.x.max, other.x.max),
@@ -113,59 +135,262 @@ impl Cuboid {
         max: i64::min(self.z.max, other.z.max),
       },
       off: Vec::new()
+    })
+  }
+
+  fn contains(&self, point: &Point) -> bool {
+    self.x.contains(point.x) && self.y.contains(point.y) && self.z.contains(point.z)
+  }
+
+  fn hyperbox(&self) -> HyperBox<3> {
+    HyperBox::new([self.x.min, self.y.min, self.z.min], [self.x.max, self.y.max, self.z.max])
+  }
+
+  // Points in the outer box that are not in an off cuboid
+  fn is_on(&self, point: &Point) -> bool {
+    self.contains(point) && !self.off.iter().any(|c|c.is_on(point))
+  }
+
+  fn volume_in(&self, query: &Cuboid) -> u128 {
+    let overlap = match self.intersection(query) {
+      Some(c) => c,
+      None => return 0,
+    };
+    let off_volume: u128 = self.off.iter().map(|c|c.volume_in(query)).sum();
+    overlap.outer_volume() - off_volume
+  }
+
+  fn lit_boxes(&self) -> BoxSet<3> {
+    let mut lit = BoxSet::from_box(self.hyperbox());
+    for c in &self.off {
+      lit = lit.difference(&c.lit_boxes());
+    }
+    return lit;
+  }
+
+  fn subtract(&mut self, other: &Cuboid) {
+    let intersect_cube = match self.intersection(other) {
+      Some(c) => c,
+      None => return,
     };
     self.off.iter_mut().for_each(|c|c.subtract(other));
     self.off.push(intersect_cube);
   }
 
+  // Volume ignoring the off cuboids
+  fn outer_volume(&self) -> u128 {
+    (self.x.max - self.x.min + 1) as u128
+    * (self.y.max - self.y.min + 1) as u128
+    * (self.z.max - self.z.min + 1) as u128
+  }
+
   fn volume(&self) -> u128 {
     let off_volume : u128= self.off.iter().map(|c|c.volume()).sum();
-    ((self.x.max - self.x.min + 1) as u128
-    * (self.y.max - self.y.min + 1) as u128
-    * (self.z.max - self.z.min + 1) as u128)
-    - off_volume
+    self.outer_volume() - off_volume
   }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Engine {
+  // each lit cuboid keeps the cuboids turned off inside it, recursively
+  Nested,
+  // inclusion-exclusion: every overlap is added back with the opposite sign
+  SignedVolumes,
+  // lit cubes as a set of disjoint boxes, split around each instruction
+  BoxSet,
+}
 
-fn solution_1(data: &Vec<String>) -> u128 {
-  let valid_cuboid = Cuboid {
-    x: Range::new(-50, 50),
-    y: Range::new(-50, 50),
-    z: Range::new(-50, 50),
-    off: Vec::new()
-  };
-  let instructions = parse_instructions(data);
+// Reactor state after applying instructions, lit cuboids are disjoint
+struct Reactor {
+  cubes: Vec<Cuboid>,
+  // instructions are clipped to the region, if any
+  region: Option<Cuboid>,
+  lit: u128,
+}
 
-  let mut cubes: Vec<Cuboid> = Vec::new();
-  instructions.iter().filter(|i|i.cuboid.intersects(&valid_cuboid)).for_each(|i|{
-    let new_cube = i.cuboid.clone();
-    cubes.iter_mut().for_each(|c|c.subtract(&new_cube));
-    if i.on {
-      cubes.push(new_cube);
+impl Reactor {
+  fn new() -> Self {
+    Self {
+      cubes: Vec::new(),
+      region: None,
+      lit: 0,
     }
-  });
+  }
+
+  fn with_region(region: &Cuboid) -> Self {
+    Self {
+      cubes: Vec::new(),
+      region: Some(region.clone()),
+      lit: 0,
+    }
+  }
 
-  return cubes.iter().map(|c|c.volume()).sum();
+  // Applies a single instruction, returning how many cubes are on afterwards
+  fn apply(&mut self, instruction: &Instruction) -> u128 {
+    let new_cube = match &self.region {
+      Some(region) => match instruction.cuboid.intersection(region) {
+        Some(c) => c,
+        None => return self.lit,
+      },
+      None => instruction.cuboid.clone(),
+    };
+    self.lit -= self.lit_in(&new_cube);
+    self.cubes.iter_mut().for_each(|c|c.subtract(&new_cube));
+    if instruction.on {
+      self.lit += new_cube.outer_volume();
+      self.cubes.push(new_cube);
+    }
+    return self.lit;
+  }
+
+  fn volume(&self) -> u128 {
+    self.cubes.iter().map(|c|c.volume()).sum()
+  }
+
+  fn is_on(&self, point: &Point) -> bool {
+    self.cubes.iter().any(|c|c.is_on(point))
+  }
+
+  fn lit_in(&self, query: &Cuboid) -> u128 {
+    self.cubes.iter().map(|c|c.volume_in(query)).sum()
+  }
+
+  fn lit_boxes(&self) -> BoxSet<3> {
+    self.cubes.iter().fold(BoxSet::new(), |lit, c|lit.union(&c.lit_boxes()))
+  }
 }
 
-fn solution_2(data: &Vec<String>) -> u128 {
-  let instructions = parse_instructions(data);
+fn reboot_nested(instructions: &[Instruction]) -> u128 {
+  let mut reactor = Reactor::new();
+  for i in instructions {
+    reactor.apply(i);
+  }
 
-  let mut cubes: Vec<Cuboid> = Vec::new();
-  instructions.iter().for_each(|i|{
-    let new_cube = i.cuboid.clone();
-    cubes.iter_mut().for_each(|c|c.subtract(&new_cube));
+  return reactor.volume();
+}
+
+fn reboot_signed_volumes(instructions: &[Instruction]) -> u128 {
+  // identical cuboids are merged by summing their signs, so the list stays small
+  let mut sign_by_bounds: HashMap<[i64; 6], i128> = HashMap::new();
+  for i in instructions {
+    // cancel whatever is already counted inside the new cuboid
+    let mut updates: HashMap<[i64; 6], i128> = HashMap::new();
+    for (bounds, sign) in &sign_by_bounds {
+      if let Some(overlap) = Cuboid::from_bounds(bounds).intersection(&i.cuboid) {
+        *updates.entry(overlap.bounds()).or_insert(0) -= sign;
+      }
+    }
     if i.on {
-      cubes.push(new_cube);
+      *updates.entry(i.cuboid.bounds()).or_insert(0) += 1;
     }
-  });
+    for (bounds, sign) in updates {
+      *sign_by_bounds.entry(bounds).or_insert(0) += sign;
+    }
+    sign_by_bounds.retain(|_, sign|*sign != 0);
+  }
+
+  let volume: i128 = sign_by_bounds
+    .iter()
+    .map(|(bounds, sign)|Cuboid::from_bounds(bounds).outer_volume() as i128 * sign)
+    .sum();
+  return volume as u128;
+}
+
+fn reboot_box_set(instructions: &[Instruction]) -> u128 {
+  let mut lit: BoxSet<3> = BoxSet::new();
+  for i in instructions {
+    if i.on {
+      lit.insert(&i.cuboid.hyperbox());
+    } else {
+      lit.remove(&i.cuboid.hyperbox());
+    }
+  }
+  return lit.volume().unwrap();
+}
+
+fn reboot(instructions: &[Instruction], engine: Engine) -> u128 {
+  match engine {
+    Engine::Nested => reboot_nested(instructions),
+    Engine::SignedVolumes => reboot_signed_volumes(instructions),
+    Engine::BoxSet => reboot_box_set(instructions),
+  }
+}
+
+fn query_reactor(data: &Vec<String>) {
+  let mut reactor = Reactor::new();
+  for i in parse_instructions(data) {
+    reactor.apply(&i);
+  }
+
+  let origin = Point { x: 0, y: 0, z: 0 };
+  println!("Cube {:?} is on: {}", origin, reactor.is_on(&origin));
+  let region = Cuboid::from_bounds(&[-50, 50, -50, 50, -50, 50]);
+  println!("Cubes on in {:?}: {}", region.bounds(), reactor.lit_in(&region));
+
+  let lit_boxes = reactor.lit_boxes();
+  let lit_volume: u128 = lit_boxes.volume().unwrap();
+  println!("{} disjoint lit cuboids, {} cubes on", lit_boxes.boxes().len(), lit_volume);
+  assert_eq!(lit_volume, reactor.volume(), "Lit cuboids don't cover the reactor volume");
+  assert_eq!(lit_boxes.contains(&[0, 0, 0]), reactor.is_on(&origin), "Lit cuboids disagree on the origin");
+  let lit_in_region = lit_boxes.intersection(&BoxSet::from_box(region.hyperbox())).volume().unwrap();
+  assert_eq!(lit_in_region, reactor.lit_in(&region), "Lit cuboids disagree on the region");
+}
+
+fn print_reboot_steps(data: &Vec<String>, region: &Cuboid) {
+  let mut reactor = Reactor::with_region(region);
+  for (step, instruction) in parse_instructions(data).iter().enumerate() {
+    let lit = reactor.apply(instruction);
+    println!("Step {}: {} {:?} -> {} cubes on", step + 1, if instruction.on { "on" } else { "off" }, instruction.cuboid.bounds(), lit);
+  }
+  assert_eq!(reactor.lit, reactor.volume(), "Running count doesn't match the reactor volume");
+}
+
+// Runs every engine on the same instructions, checking they agree
+fn benchmark(data: &Vec<String>) {
+  let instructions = parse_instructions(data);
+  let mut results: Vec<u128> = Vec::new();
+  for engine in [Engine::Nested, Engine::SignedVolumes, Engine::BoxSet] {
+    let start = Instant::now();
+    let result = reboot(&instructions, engine);
+    println!("{:?} engine: {} cubes on in {:?}", engine, result, start.elapsed());
+    results.push(result);
+  }
+  assert!(results.iter().all(|r|*r == results[0]), "Engines disagree");
+
+}
+
+// Keeps only the part of each instruction inside the region
+fn clip_instructions(instructions: &[Instruction], region: &Cuboid) -> Vec<Instruction> {
+  instructions
+    .iter()
+    .filter_map(|i|i.cuboid.intersection(region).map(|cuboid|Instruction { cuboid, on: i.on }))
+    .collect()
+}
+
+fn solution_1(data: &Vec<String>, region: &Cuboid, engine: Engine) -> u128 {
+  let instructions = clip_instructions(&parse_instructions(data), region);
+
+  return reboot(&instructions, engine);
+}
+
+fn solution_2(data: &Vec<String>, engine: Engine) -> u128 {
+  let instructions = parse_instructions(data);
 
-  return cubes.iter().map(|c|c.volume()).sum();
+  return reboot(&instructions, engine);
 }
 
 fn main() {
   let test = true;
+  let engine = Engine::Nested;
+  let compare_engines = false;
+  let query = false;
+  let print_steps = false;
+  let initialization_region = Cuboid {
+    x: Range::new(-50, 50),
+    y: Range::new(-50, 50),
+    z: Range::new(-50, 50),
+    off: Vec::new()
+  };
   let mut file_path: String = "inputs/day22".to_string();
   let mut emoji: String = "🎉".to_string();
   if test {
@@ -180,7 +405,125 @@ fn main() {
               .map(|line|line.unwrap())
               .collect();
 
-  println!(r#"{:?} Part 1 result is {:?}"#, emoji, solution_1(&data));
+  println!(r#"{:?} Part 1 result is {:?}"#, emoji, solution_1(&data, &initialization_region, engine));
+
+  println!(r#"{:?} Part 2 test result is {:?}"#, emoji, solution_2(&data, engine));
+
+  if compare_engines {
+    benchmark(&data);
+  }
+
+  if query {
+    query_reactor(&data);
+  }
+
+  if print_steps {
+    print_reboot_steps(&data, &initialization_region);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::collections::HashSet;
+
+  const ENGINES: [Engine; 3] = [Engine::Nested, Engine::SignedVolumes, Engine::BoxSet];
 
-  println!(r#"{:?} Part 2 test result is {:?}"#, emoji, solution_2(&data));
+  fn instructions(lines: &[&str]) -> Vec<Instruction> {
+    parse_instructions(&lines.iter().map(|l|l.to_string()).collect())
+  }
+
+  // Switches every cube one by one, only for small cuboids
+  fn brute_force(instructions: &[Instruction]) -> u128 {
+    let mut lit: HashSet<(i64, i64, i64)> = HashSet::new();
+    for i in instructions {
+      let c = &i.cuboid;
+      for x in c.x.min..=c.x.max {
+        for y in c.y.min..=c.y.max {
+          for z in c.z.min..=c.z.max {
+            if i.on {
+              lit.insert((x, y, z));
+            } else {
+              lit.remove(&(x, y, z));
+            }
+          }
+        }
+      }
+    }
+    lit.len() as u128
+  }
+
+  fn assert_engines_agree(instructions: &[Instruction], expected: u128) {
+    for engine in ENGINES {
+      assert_eq!(reboot(instructions, engine), expected, "{:?} engine", engine);
+    }
+  }
+
+  #[test]
+  fn engines_agree_on_test_input() {
+    let file = File::open("inputs/day22.test.txt").unwrap();
+    let data: Vec<String> = io::BufReader::new(file)
+                .lines()
+                .map(|line|line.unwrap())
+                .collect();
+    let instructions = parse_instructions(&data);
+    let expected = reboot(&instructions, Engine::Nested);
+    assert_engines_agree(&instructions, expected);
+
+    let region = Cuboid::from_bounds(&[-50, 50, -50, 50, -50, 50]);
+    let clipped = clip_instructions(&instructions, &region);
+    assert_engines_agree(&clipped, reboot(&clipped, Engine::Nested));
+  }
+
+  #[test]
+  fn engines_agree_on_overlapping_cuboids() {
+    let sequences: Vec<Vec<&str>> = vec![
+      // puzzle statement example
+      vec![
+        "on x=10..12,y=10..12,z=10..12",
+        "on x=11..13,y=11..13,z=11..13",
+        "off x=9..11,y=9..11,z=9..11",
+        "on x=10..10,y=10..10,z=10..10",
+      ],
+      // hole punched in the middle, then partly refilled
+      vec![
+        "on x=0..4,y=0..4,z=0..4",
+        "off x=1..3,y=1..3,z=1..3",
+        "on x=2..6,y=2..2,z=0..4",
+      ],
+      // the same cuboid switched on and off repeatedly
+      vec![
+        "on x=-2..2,y=-2..2,z=-2..2",
+        "on x=-2..2,y=-2..2,z=-2..2",
+        "off x=-2..2,y=-2..2,z=-2..2",
+        "on x=-2..2,y=-2..2,z=-2..2",
+        "off x=-1..1,y=-5..5,z=-1..1",
+      ],
+      // chain of overlapping cuboids, turning off what is already off
+      vec![
+        "off x=0..3,y=0..3,z=0..3",
+        "on x=0..3,y=0..3,z=0..3",
+        "on x=2..5,y=1..4,z=2..5",
+        "on x=4..7,y=2..5,z=4..7",
+        "off x=3..4,y=0..9,z=3..4",
+        "off x=20..21,y=20..21,z=20..21",
+        "on x=1..6,y=3..3,z=1..6",
+      ],
+    ];
+    for lines in sequences {
+      let instructions = instructions(&lines);
+      assert_engines_agree(&instructions, brute_force(&instructions));
+    }
+  }
+
+  #[test]
+  fn statement_example_count() {
+    let instructions = instructions(&[
+      "on x=10..12,y=10..12,z=10..12",
+      "on x=11..13,y=11..13,z=11..13",
+      "off x=9..11,y=9..11,z=9..11",
+      "on x=10..10,y=10..10,z=10..10",
+    ]);
+    assert_engines_agree(&instructions, 39);
+  }
 }
@@ -0,0 +1,243 @@
+// Axis aligned boxes in D dimensions with inclusive integer bounds,
+// and sets of disjoint boxes supporting union, intersection and difference
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HyperBox<const D: usize> {
+  min: [i64; D],
+  max: [i64; D],
+}
+
+impl<const D: usize> HyperBox<D> {
+  pub fn new(min: [i64; D], max: [i64; D]) -> Self {
+    for axis in 0..D {
+      if min[axis] > max[axis] {
+        panic!("Invalid bounds {} > {} on axis {}", min[axis], max[axis], axis);
+      }
+    }
+    Self {
+      min,
+      max,
+    }
+  }
+
+  pub fn contains(&self, point: &[i64; D]) -> bool {
+    (0..D).all(|axis|self.min[axis] <= point[axis] && point[axis] <= self.max[axis])
+  }
+
+  pub fn intersects(&self, other: &HyperBox<D>) -> bool {
+    (0..D).all(|axis|self.min[axis] <= other.max[axis] && self.max[axis] >= other.min[axis])
+  }
+
+  pub fn intersection(&self, other: &HyperBox<D>) -> Option<HyperBox<D>> {
+    if !self.intersects(other) {
+      return None;
+    }
+    let mut min = self.min;
+    let mut max = self.max;
+    for axis in 0..D {
+      min[axis] = i64::max(self.min[axis], other.min[axis]);
+      max[axis] = i64::min(self.max[axis], other.max[axis]);
+    }
+    Some(HyperBox { min, max })
+  }
+
+  // Self minus other, as up to 2*D disjoint boxes: two slabs per axis around the overlap
+  pub fn difference(&self, other: &HyperBox<D>) -> Vec<HyperBox<D>> {
+    let overlap = match self.intersection(other) {
+      Some(b) => b,
+      None => return vec![*self],
+    };
+    let mut pieces: Vec<HyperBox<D>> = Vec::new();
+    let mut rest = *self;
+    for axis in 0..D {
+      if rest.min[axis] < overlap.min[axis] {
+        let mut piece = rest;
+        piece.max[axis] = overlap.min[axis] - 1;
+        pieces.push(piece);
+      }
+      if overlap.max[axis] < rest.max[axis] {
+        let mut piece = rest;
+        piece.min[axis] = overlap.max[axis] + 1;
+        pieces.push(piece);
+      }
+      rest.min[axis] = overlap.min[axis];
+      rest.max[axis] = overlap.max[axis];
+    }
+    return pieces;
+  }
+
+  // Number of integer points in the box, None if it doesn't fit in a u128
+  pub fn volume(&self) -> Option<u128> {
+    let mut volume: u128 = 1;
+    for axis in 0..D {
+      let length = (self.max[axis] as i128 - self.min[axis] as i128 + 1) as u128;
+      volume = volume.checked_mul(length)?;
+    }
+    Some(volume)
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BoxSet<const D: usize> {
+  // disjoint boxes
+  boxes: Vec<HyperBox<D>>,
+}
+
+impl<const D: usize> BoxSet<D> {
+  pub fn new() -> Self {
+    Self {
+      boxes: Vec::new(),
+    }
+  }
+
+  pub fn from_box(hyperbox: HyperBox<D>) -> Self {
+    Self {
+      boxes: vec![hyperbox],
+    }
+  }
+
+  pub fn boxes(&self) -> &[HyperBox<D>] {
+    &self.boxes
+  }
+
+  pub fn contains(&self, point: &[i64; D]) -> bool {
+    self.boxes.iter().any(|b|b.contains(point))
+  }
+
+  pub fn insert(&mut self, hyperbox: &HyperBox<D>) {
+    self.remove(hyperbox);
+    self.boxes.push(*hyperbox);
+  }
+
+  pub fn remove(&mut self, hyperbox: &HyperBox<D>) {
+    self.boxes = self.boxes
+      .iter()
+      .flat_map(|b|b.difference(hyperbox))
+      .collect();
+  }
+
+  pub fn union(&self, other: &BoxSet<D>) -> BoxSet<D> {
+    let mut union = self.clone();
+    for b in &other.boxes {
+      union.insert(b);
+    }
+    return union;
+  }
+
+  pub fn intersection(&self, other: &BoxSet<D>) -> BoxSet<D> {
+    // both sets are disjoint, so are the pairwise intersections
+    let boxes: Vec<HyperBox<D>> = self.boxes
+      .iter()
+      .flat_map(|a|other.boxes.iter().filter_map(move |b|a.intersection(b)))
+      .collect();
+    BoxSet { boxes }
+  }
+
+  pub fn difference(&self, other: &BoxSet<D>) -> BoxSet<D> {
+    let mut difference = self.clone();
+    for b in &other.boxes {
+      difference.remove(b);
+    }
+    return difference;
+  }
+
+  // Number of integer points in the set, None if it doesn't fit in a u128
+  pub fn volume(&self) -> Option<u128> {
+    let mut volume: u128 = 0;
+    for b in &self.boxes {
+      volume = volume.checked_add(b.volume()?)?;
+    }
+    Some(volume)
+  }
+}
+
+impl<const D: usize> Default for BoxSet<D> {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn set<const D: usize>(min: [i64; D], max: [i64; D]) -> BoxSet<D> {
+    BoxSet::from_box(HyperBox::new(min, max))
+  }
+
+  fn assert_disjoint<const D: usize>(set: &BoxSet<D>) {
+    for (i, a) in set.boxes.iter().enumerate() {
+      for b in &set.boxes[i + 1..] {
+        assert!(!a.intersects(b), "{:?} and {:?} overlap", a, b);
+      }
+    }
+  }
+
+  #[test]
+  fn one_dimension() {
+    // meetings 9..12 and 11..14 booked, 10..13 cancelled
+    let booked = set([9], [12]).union(&set([11], [14]));
+    let cancelled = set([10], [13]);
+    assert_eq!(booked.volume(), Some(6));
+    assert_eq!(booked.difference(&cancelled).volume(), Some(2));
+    assert_eq!(booked.intersection(&cancelled).volume(), Some(4));
+    assert!(!booked.difference(&cancelled).contains(&[11]));
+    assert!(booked.difference(&cancelled).contains(&[14]));
+  }
+
+  #[test]
+  fn two_dimensions() {
+    // two 3x3 squares sharing a 2x2 corner, and a hole in the middle of the first
+    let a = set([0, 0], [2, 2]);
+    let b = set([1, 1], [3, 3]);
+    assert_eq!(a.union(&b).volume(), Some(14));
+    assert_eq!(a.intersection(&b).volume(), Some(4));
+    assert_eq!(a.difference(&b).volume(), Some(5));
+    let hole = set([1, 1], [1, 1]);
+    assert_eq!(a.difference(&hole).volume(), Some(8));
+    assert!(!a.difference(&hole).contains(&[1, 1]));
+  }
+
+  #[test]
+  fn four_dimensions() {
+    // a 2^4 hypercube and a 5^4 one sharing a corner
+    let cube = set([0, 0, 0, 0], [1, 1, 1, 1]);
+    let corner = set([1, 1, 1, 1], [5, 5, 5, 5]);
+    assert_eq!(cube.difference(&corner).volume(), Some(15));
+    assert_eq!(cube.intersection(&corner).volume(), Some(1));
+    assert_eq!(cube.union(&corner).volume(), Some(16 + 625 - 1));
+  }
+
+  #[test]
+  fn results_stay_disjoint() {
+    let a = set([0, 0, 0], [4, 4, 4]).union(&set([2, -3, 1], [7, 3, 2])).union(&set([-1, 1, 3], [1, 6, 9]));
+    let b = set([1, 1, 1], [3, 3, 3]).union(&set([-2, 0, 0], [0, 8, 4]));
+    assert_disjoint(&a);
+    assert_disjoint(&b);
+    assert_disjoint(&a.union(&b));
+    assert_disjoint(&a.intersection(&b));
+    assert_disjoint(&a.difference(&b));
+    assert_disjoint(&b.difference(&a));
+
+    // |a ∪ b| = |a| + |b| - |a ∩ b| and |a \ b| = |a| - |a ∩ b|
+    let (va, vb) = (a.volume().unwrap(), b.volume().unwrap());
+    let both = a.intersection(&b).volume().unwrap();
+    assert_eq!(a.union(&b).volume(), Some(va + vb - both));
+    assert_eq!(a.difference(&b).volume(), Some(va - both));
+  }
+
+  #[test]
+  fn volume_overflow() {
+    // 2^128 points don't fit in a u128
+    let plane = HyperBox::new([i64::MIN; 2], [i64::MAX; 2]);
+    assert_eq!(plane.volume(), None);
+
+    // each half plane has 2^127 points, both together overflow
+    let left = HyperBox::new([i64::MIN, i64::MIN], [-1, i64::MAX]);
+    let right = HyperBox::new([0, i64::MIN], [i64::MAX, i64::MAX]);
+    assert_eq!(left.volume(), Some(1 << 127));
+    let halves = BoxSet::from_box(left).union(&BoxSet::from_box(right));
+    assert_disjoint(&halves);
+    assert_eq!(halves.volume(), None);
+  }
+}
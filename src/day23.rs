@@ -1,115 +1,82 @@
-use std::collections::{HashMap, HashSet};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
 use std::fmt;
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::{self, BufRead};
-use lazy_static::lazy_static;
-
-static BOARD_WIDTH: i64 = 13;
-static  BOARD_HEIGHT_2: i64 = 7;
-
-lazy_static! {
-  static ref COST_BY_LETTER: HashMap<char, i64> = HashMap::from([
-    ('A', 1),
-    ('B', 10),
-    ('C', 100),
-    ('D', 1000)
-  ]);
-
-  static ref HALLWAY: Vec<Point> = Vec::from([
-    Point::new(1,1),
-    Point::new(1,2),
-    Point::new(1,4),
-    Point::new(1,6),
-    Point::new(1,8),
-    Point::new(1,10),
-    Point::new(1,11),
-  ]);
-
-  static ref TARGET_ROOM_BY_LETTER: HashMap<char, i64> = HashMap::from([
-    ('A', 3),
-    ('B', 5),
-    ('C', 7),
-    ('D', 9),
-  ]);
-
-
-  static ref TARGET_BY_DEPTH: HashMap<i64,HashMap<Point, char>> =  HashMap::from([
-    (2, HashMap::from([
-    // room 1
-    (Point::new(2,3), 'A'),
-    (Point::new(3,3), 'A'),
-    // room 2
-    (Point::new(2,5), 'B'),
-    (Point::new(3,5), 'B'),
-    // room 3
-    (Point::new(2,7), 'C'),
-    (Point::new(3,7), 'C'),
-    // room 4
-    (Point::new(2,9), 'D'),
-    (Point::new(3,9), 'D'),
-    ])),
-    (4, HashMap::from([
-      // room 1
-      (Point::new(2,3), 'A'),
-      (Point::new(3,3), 'A'),
-      (Point::new(4,3), 'A'),
-      (Point::new(5,3), 'A'),
-      // room 2
-      (Point::new(2,5), 'B'),
-      (Point::new(3,5), 'B'),
-      (Point::new(4,5), 'B'),
-      (Point::new(5,5), 'B'),
-      // room 3
-      (Point::new(2,7), 'C'),
-      (Point::new(3,7), 'C'),
-      (Point::new(4,7), 'C'),
-      (Point::new(5,7), 'C'),
-      // room 4
-      (Point::new(2,9), 'D'),
-      (Point::new(3,9), 'D'),
-      (Point::new(4,9), 'D'),
-      (Point::new(5,9), 'D'),
-    ])),
-  ]);
-
-  static ref WALLS_2: HashSet<Point> = build_walls();
+use std::rc::Rc;
+use std::thread;
+use std::time::Duration;
+use regex::Regex;
+
+// Layout of the burrow, read from the input: rooms are the columns open below the
+// hallway, from left to right they belong to A, B, C... costing 1, 10, 100...
+#[derive(Debug, PartialEq, Eq)]
+struct Burrow {
+  walls: HashSet<Point>,
+  open_cells: HashSet<Point>,
+  width: i64,
+  height: i64,
+  // hallway cells amphipods can stop on, from left to right
+  hallway: Vec<Point>,
+  room_col_by_letter: HashMap<char, i64>,
+  cost_by_letter: HashMap<char, i64>,
+  room_depth: i64,
 }
 
-fn build_walls() -> HashSet<Point> {
-  let mut walls: HashSet<Point> = HashSet::new();
+impl Burrow {
+  fn parse(data: &Vec<String>) -> Result<Self, String> {
+    let mut walls: HashSet<Point> = HashSet::new();
+    let mut open_cells: HashSet<Point> = HashSet::new();
+    for (r, line) in data.iter().enumerate() {
+      for (c, cell) in line.chars().enumerate() {
+        let point = Point::new(r as i64, c as i64);
+        match cell {
+          '#' => { walls.insert(point); },
+          ' ' => continue,
+          _ => { open_cells.insert(point); },
+        }
+      }
+    }
 
-  // row 0
-  for col in 0..BOARD_WIDTH {
-    walls.insert(Point::new(0, col));
-  }
+    let mut room_cols: Vec<i64> = open_cells.iter().filter(|p|p.row == 2).map(|p|p.col).collect();
+    room_cols.sort();
+    if room_cols.is_empty() {
+      return Err("No room below the hallway".to_string());
+    }
+    let room_depth = open_cells.iter().filter(|p|p.row > 1 && p.col == room_cols[0]).count() as i64;
+    for col in &room_cols {
+      let depth = open_cells.iter().filter(|p|p.row > 1 && p.col == *col).count() as i64;
+      if depth != room_depth {
+        return Err(format!("Room in column {} is {} deep, other rooms are {} deep", col, depth, room_depth));
+      }
+    }
 
-  // left 
-  walls.insert(Point::new(1,0));
-  walls.insert(Point::new(2,0));
-  walls.insert(Point::new(2,1));
-  for row in 2..BOARD_HEIGHT_2 {
-    walls.insert(Point::new(row,2));
-  }
-  // floor
-  for col in 3..BOARD_WIDTH - 3 {
-    walls.insert(Point::new(BOARD_HEIGHT_2 -1, col));
-  }
-  // right
-  for row in 2..BOARD_HEIGHT_2 {
-    walls.insert(Point::new(row,BOARD_WIDTH - 3));
-  }
-  walls.insert(Point::new(1,BOARD_WIDTH - 1));
-  walls.insert(Point::new(2,BOARD_WIDTH - 1));
-  walls.insert(Point::new(2,BOARD_WIDTH - 2));
+    let mut hallway: Vec<Point> = open_cells
+      .iter()
+      .filter(|p|p.row == 1 && !room_cols.contains(&p.col))
+      .cloned()
+      .collect();
+    hallway.sort_by_key(|p|p.col);
+
+    let mut room_col_by_letter: HashMap<char, i64> = HashMap::new();
+    let mut cost_by_letter: HashMap<char, i64> = HashMap::new();
+    for (i, col) in room_cols.iter().enumerate() {
+      let letter = (b'A' + i as u8) as char;
+      room_col_by_letter.insert(letter, *col);
+      cost_by_letter.insert(letter, 10_i64.pow(i as u32));
+    }
 
-  // room walls
-  for row in 2..BOARD_HEIGHT_2 -1 {
-    walls.insert(Point::new(row, 4));
-    walls.insert(Point::new(row, 6));
-    walls.insert(Point::new(row, 8));
+    Ok(Self {
+      walls,
+      open_cells,
+      width: data.iter().map(|line|line.len()).max().unwrap() as i64,
+      height: data.len() as i64,
+      hallway,
+      room_col_by_letter,
+      cost_by_letter,
+      room_depth,
+    })
   }
-
-  return walls;
 }
 
 #[derive(Debug, Clone)]
@@ -125,8 +92,30 @@ impl Step {
     + (self.from.col - self.to.col).abs()
   }
 
-  fn cost(&self) -> i64 {
-    self.distance() * COST_BY_LETTER[&self.letter]
+  fn cost(&self, burrow: &Burrow) -> i64 {
+    self.distance() * burrow.cost_by_letter[&self.letter]
+  }
+
+  // B: (3,7) -> (1,4) cost 40
+  fn to_text(&self, burrow: &Burrow) -> String {
+    format!("{}: ({},{}) -> ({},{}) cost {}",
+      self.letter, self.from.row, self.from.col, self.to.row, self.to.col, self.cost(burrow))
+  }
+
+  // Returns the step and the cost written next to it
+  fn parse(line: &str) -> Result<(Step, i64), String> {
+    let re = Regex::new(r"^([A-Z]): \((\d+),(\d+)\) -> \((\d+),(\d+)\) cost (\d+)$").unwrap();
+    let captures = match re.captures(line.trim()) {
+      Some(c) => c,
+      None => return Err(format!("Cannot parse move `{}`", line)),
+    };
+    let number = |i: usize| i64::from_str_radix(&captures[i], 10).unwrap();
+    let step = Step {
+      letter: captures[1].chars().next().unwrap(),
+      from: Point::new(number(2), number(3)),
+      to: Point::new(number(4), number(5)),
+    };
+    return Ok((step, number(6)));
   }
 }
 
@@ -146,57 +135,67 @@ impl Point {
 struct Board {
   original: HashMap<Point, char>,
   amphipods: HashMap<Point, char>,
-  room_depth: i64,
+  burrow: Rc<Burrow>,
 }
 
 
 impl Board {  
-  fn parse(data: &Vec<String>) -> Self {
+  fn parse(data: &Vec<String>) -> Result<Self, String> {
     let mut amphipods: HashMap<Point, char> = HashMap::new();
     for r in 0..data.len() {
       let row: Vec<char> = data[r].chars().collect();
       for c in 0..row.len() {
         let letter = row[c];
-        match letter {
-            'A' | 'B' | 'C' | 'D' => {
-              amphipods.insert(Point{ row: r as i64, col: c as i64}, letter);
-            },
-            _ => continue
+        if letter.is_ascii_uppercase() {
+          amphipods.insert(Point{ row: r as i64, col: c as i64}, letter);
         }
       }
     }
-    Self {
+    let burrow = Burrow::parse(data)?;
+    if let Some(letter) = amphipods.values().find(|l|!burrow.room_col_by_letter.contains_key(l)) {
+      return Err(format!("No room for amphipod {}", letter));
+    }
+    // each room must end up exactly full
+    let mut letters: Vec<&char> = burrow.room_col_by_letter.keys().collect();
+    letters.sort();
+    for letter in letters {
+      let count = amphipods.values().filter(|l|*l == letter).count() as i64;
+      if count != burrow.room_depth {
+        return Err(format!("Found {} amphipods {} for a room {} deep", count, letter, burrow.room_depth));
+      }
+    }
+    Ok(Self {
       original: amphipods.clone(),
       amphipods,
-      room_depth: (data.len() - 3) as i64
-    }
+      burrow: Rc::new(burrow),
+    })
   } 
 
   fn is_in_hallway(point: &Point) -> bool {
     point.row == 1
   }
 
-  fn left_hallway(point: &Point) -> Vec<Point> {
-    HALLWAY.iter().filter(|p|p.col < point.col)
+  fn left_hallway(&self, point: &Point) -> Vec<Point> {
+    self.burrow.hallway.iter().filter(|p|p.col < point.col)
     .map(|p|p.clone())
     .rev()
     .collect()
   }
 
-  fn right_hallway(point: &Point) -> Vec<Point> {
-    HALLWAY.iter().filter(|p|p.col > point.col)
+  fn right_hallway(&self, point: &Point) -> Vec<Point> {
+    self.burrow.hallway.iter().filter(|p|p.col > point.col)
     .map(|p|p.clone())
     .collect()
   }
 
   fn can_move_left(&self, point: &Point) -> bool {
-    Self::left_hallway(point).first().is_some() &&
-    !self.amphipods.contains_key(Self::left_hallway(point).first().unwrap())
+    self.left_hallway(point).first().is_some() &&
+    !self.amphipods.contains_key(self.left_hallway(point).first().unwrap())
   }
 
   fn can_move_right(&self, point: &Point) -> bool {
-    Self::right_hallway(point).first().is_some() &&
-    !self.amphipods.contains_key(Self::right_hallway(point).first().unwrap())
+    self.right_hallway(point).first().is_some() &&
+    !self.amphipods.contains_key(self.right_hallway(point).first().unwrap())
   }
 
   fn can_move_to_target_room(&self, letter: &char, point: &Point) -> bool {
@@ -205,20 +204,20 @@ impl Board {
     }
 
     // check all hallway points are available
-    let target_room: i64 = TARGET_ROOM_BY_LETTER[&letter];
+    let target_room: i64 = self.burrow.room_col_by_letter[&letter];
     if target_room == point.col {
       return false;
     }
 
     // is hallway slice accessible
     if target_room < point.col {
-      if Self::left_hallway(point).iter()
+      if self.left_hallway(point).iter()
       .filter(|x|x.col > target_room)
       .any(|x|self.amphipods.contains_key(x)) {
         return false;
       }
     } else {
-      if Self::right_hallway(point).iter()
+      if self.right_hallway(point).iter()
       .filter(|x|x.col < target_room)
       .any(|x|self.amphipods.contains_key(x)) {
         return false;
@@ -252,16 +251,16 @@ impl Board {
   }
 
   fn is_done(&self, letter: &char, point: &Point) -> bool {
-    point.col == TARGET_ROOM_BY_LETTER[letter] 
+    point.col == self.burrow.room_col_by_letter[letter] 
     && point.row > 1
     && self.amphipods.clone().into_iter()
-    .filter(|(other_point, _)| other_point.col == TARGET_ROOM_BY_LETTER[letter])
+    .filter(|(other_point, _)| other_point.col == self.burrow.room_col_by_letter[letter])
     .all(|(_,l)| l == *letter)
   }
 
   fn deepest_cell_in_target_room(&self, letter: &char) -> Point {
-    for row in 0..self.room_depth {
-      let possible_move= Point::new(5 - row, TARGET_ROOM_BY_LETTER[letter]);
+    for row in 0..self.burrow.room_depth {
+      let possible_move= Point::new(self.burrow.room_depth + 1 - row, self.burrow.room_col_by_letter[letter]);
       if self.amphipods.contains_key(&possible_move) {
         continue
       }
@@ -299,7 +298,7 @@ impl Board {
 
     // move to hallway
     // move left
-    for cell in Self::left_hallway(point) {
+    for cell in self.left_hallway(point) {
       if self.amphipods.contains_key(&cell) {
         break;
       }
@@ -307,7 +306,7 @@ impl Board {
     }
 
     // move right
-    for cell in Self::right_hallway(point) {
+    for cell in self.right_hallway(point) {
       if self.amphipods.contains_key(&cell) {
         break;
       }
@@ -322,12 +321,59 @@ impl Board {
     let letter = self.amphipods.get(from).unwrap().clone();
     new_amphipods.remove(from);
     new_amphipods.insert(to.clone(), letter);
-    return Self {amphipods: new_amphipods, original: self.original.clone(), room_depth: self.room_depth};
+    return Self {amphipods: new_amphipods, original: self.original.clone(), burrow: self.burrow.clone()};
+  }
+
+  // Cells an amphipod can stand on: the hallway first, then the rooms row by row
+  fn cells(&self) -> Vec<Point> {
+    let mut cells: Vec<Point> = self.burrow.hallway.clone();
+    let mut room_cols: Vec<i64> = self.burrow.room_col_by_letter.values().cloned().collect();
+    room_cols.sort();
+    for row in 2..2 + self.burrow.room_depth {
+      for col in &room_cols {
+        cells.push(Point::new(row, *col));
+      }
+    }
+    return cells;
+  }
+
+  // One char per cell, '.' when empty
+  fn encode(&self, cells: &[Point]) -> String {
+    cells.iter().map(|p|*self.amphipods.get(p).unwrap_or(&'.')).collect()
+  }
+
+  fn decode(&self, cells: &[Point], encoding: &str) -> Self {
+    let amphipods: HashMap<Point, char> = cells
+      .iter()
+      .zip(encoding.chars())
+      .filter(|(_, letter)|*letter != '.')
+      .map(|(p, letter)|(p.clone(), letter))
+      .collect();
+    return Self {amphipods, original: self.original.clone(), burrow: self.burrow.clone()};
   }
 
-  fn is_space_point(&self, point: &Point) -> bool {
-    point.row > 2 && (point.col < 2 || point.col > BOARD_WIDTH - 3)
+  fn is_solved(&self) -> bool {
+    self.amphipods.iter().all(|(point, letter)|point.row > 1 && point.col == self.burrow.room_col_by_letter[letter])
   }
+
+  // Lower bound of the cost left: every amphipod not done walks to the top of its
+  // room, the ones in their room but blocking others step out and back in
+  fn min_cost_to_finish(&self) -> i64 {
+    self.amphipods
+      .iter()
+      .filter(|(point, letter)|!self.is_done(letter, point))
+      .map(|(point, letter)|{
+        let target_room = self.burrow.room_col_by_letter[letter];
+        let distance = if point.col == target_room {
+          (point.row - 1) + 2 + 1
+        } else {
+          (point.row - 1) + (point.col - target_room).abs() + 1
+        };
+        distance * self.burrow.cost_by_letter[letter]
+      })
+      .sum()
+  }
+
 }
 
 impl fmt::Display for Board {
@@ -336,14 +382,14 @@ impl fmt::Display for Board {
       // write! macro is expecting. Note that this formatting ignores the
       // various flags provided to format strings.
       let mut s = "".to_owned();
-      for row in 0..BOARD_HEIGHT_2 {
-        for col in 0..BOARD_WIDTH {
+      for row in 0..self.burrow.height {
+        for col in 0..self.burrow.width {
           let point = Point::new(row,col);
-          if WALLS_2.contains(&point) {
+          if self.burrow.walls.contains(&point) {
             s.push('#');
             continue;
           }
-          if self.is_space_point(&point) {
+          if !self.burrow.open_cells.contains(&point) {
             s.push(' ');
             continue;
           }
@@ -368,107 +414,268 @@ struct Solution {
 }
 
 impl Solution {
-  fn print(&self) {
+  fn to_text(&self) -> String {
+    self.steps
+      .iter()
+      .map(|step|step.to_text(&self.original_board.burrow) + "\n")
+      .collect()
+  }
+
+  // Shows the boards one after the other in place, waiting frame_delay between them
+  fn replay(&self, frame_delay: Duration) {
     let mut board = self.original_board.clone();
-    println!("{}", board);
-    for step in self.steps.iter() {
+    let mut cost = 0;
+    // clear the terminal and move the cursor to the top left corner
+    print!("\x1b[2J\x1b[H{}", board);
+    for (i, step) in self.steps.iter().enumerate() {
+      thread::sleep(frame_delay);
       board = board.move_amphipod(&step.from, &step.to);
-      println!("{}", board);
+      cost += step.cost(&board.burrow);
+      print!("\x1b[2J\x1b[H{}", board);
+      println!("{}/{} {}", i + 1, self.steps.len(), step.to_text(&board.burrow));
+      println!("💸 Cost: {}", cost);
+    }
+  }
+}
+
+// Replays moves written by Solution::to_text on the board, checking each one against
+// the burrow rules. Returns the total cost, or the first illegal move
+fn validate_moves(original_board: &Board, text: &str) -> Result<i64, String> {
+  let mut board = original_board.clone();
+  let mut total_cost = 0;
+  for (i, line) in text.lines().filter(|l|!l.trim().is_empty()).enumerate() {
+    let (step, cost) = Step::parse(line)?;
+    let illegal = |reason: String| Err(format!("Move {} `{}` is illegal: {}", i + 1, line.trim(), reason));
+    match board.amphipods.get(&step.from) {
+      Some(letter) if *letter == step.letter => {},
+      Some(letter) => return illegal(format!("amphipod there is {}", letter)),
+      None => return illegal("no amphipod there".to_string()),
+    }
+    if !board.possible_moves(&step.letter, &step.from).contains(&step.to) {
+      return illegal("destination not reachable".to_string());
     }
-    println!("💸 Cost: {}", self.cost);
+    if step.cost(&board.burrow) != cost {
+      return illegal(format!("cost should be {}", step.cost(&board.burrow)));
+    }
+    board = board.move_amphipod(&step.from, &step.to);
+    total_cost += cost;
+  }
+  if !board.is_solved() {
+    return Err("Amphipods are not all in their rooms after the last move".to_string());
+  }
+  return Ok(total_cost);
+}
+
+#[derive(Clone, Eq, PartialEq)]
+struct State {
+  // cost so far plus the lower bound of the cost left
+  estimate: i64,
+  cost: i64,
+  encoding: String,
+}
+
+impl Ord for State {
+  fn cmp(&self, other: &Self) -> Ordering {
+    other.estimate.cmp(&self.estimate)
+      .then_with(|| other.cost.cmp(&self.cost))
+      .then_with(|| self.encoding.cmp(&other.encoding))
+  }
+}
+
+impl PartialOrd for State {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
   }
 }
 
-fn solve(original_board: &Board, 
-  current_board: &Board, 
-  current_cost: i64, 
-  current_min: i64, 
-  current_steps: &Vec<Step>, 
-  solutions: &Vec<Solution>) -> (Vec<Solution>, i64) {
-    let mut new_solutions = solutions.clone();
-    let mut new_min = current_min.clone();
-
-    if !current_board.amphipods.iter().any(|(point, letter)|{
-      current_board.possible_moves(letter, point).len() > 0
-    }) {
-      // println!("🚫");
-      return (new_solutions, new_min);
-    }
-
-    // move while possible moves
-    for (point, letter) in &current_board.amphipods {
-      for destination in current_board.possible_moves(&letter, &point) {
-        let step = Step{
+// A* over board states, using the min cost to finish as heuristic
+fn solve(original_board: &Board) -> Option<Solution> {
+  let cells = original_board.cells();
+  let source = original_board.encode(&cells);
+  let mut heap: BinaryHeap<State> = BinaryHeap::new();
+  let mut cost_by_encoding: HashMap<String, i64> = HashMap::new();
+  let mut previous_by_encoding: HashMap<String, (String, Step)> = HashMap::new();
+
+  cost_by_encoding.insert(source.clone(), 0);
+  heap.push(State {
+    estimate: original_board.min_cost_to_finish(),
+    cost: 0,
+    encoding: source.clone(),
+  });
+
+  while let Some(State { cost, encoding, .. }) = heap.pop() {
+    if cost > cost_by_encoding[&encoding] {
+      // already reached with a lower cost
+      continue;
+    }
+
+    let board = original_board.decode(&cells, &encoding);
+    if board.is_solved() {
+      // walk back from the solved board to rebuild the steps
+      let mut steps: Vec<Step> = Vec::new();
+      let mut current = encoding;
+      while let Some((previous, step)) = previous_by_encoding.get(&current) {
+        steps.push(step.clone());
+        current = previous.clone();
+      }
+      steps.reverse();
+      return Some(Solution {
+        original_board: original_board.clone(),
+        steps,
+        cost,
+      });
+    }
+
+    for (point, letter) in &board.amphipods {
+      for destination in board.possible_moves(letter, point) {
+        let step = Step {
           from: point.clone(),
           to: destination.clone(),
-          letter: letter.clone()
+          letter: *letter,
         };
-        let mut new_steps = current_steps.clone();
-        new_steps.push(step.clone()); 
-        let new_cost = step.cost() + current_cost;
-        if new_cost > new_min {
-          // println!("💸 current min {}", current_min);
+        let new_cost = cost + step.cost(&board.burrow);
+        let new_board = board.move_amphipod(&step.from, &step.to);
+        let new_encoding = new_board.encode(&cells);
+        if new_cost >= *cost_by_encoding.get(&new_encoding).unwrap_or(&i64::MAX) {
           continue;
         }
-        let new_board = current_board.move_amphipod(&step.from, &step.to);
-        if new_board.amphipods == TARGET_BY_DEPTH[&original_board.room_depth].clone() {
-          let solution = Solution{
-            original_board: original_board.clone(),
-            steps: new_steps.clone(),
-            cost: new_cost
-          };
-          new_solutions.push(solution.clone());
-          if new_cost < new_min {
-            new_min = new_cost.clone();
-            println!("✨ {}", new_min);
-            solution.print();
-          }
-          continue;
+        cost_by_encoding.insert(new_encoding.clone(), new_cost);
+        previous_by_encoding.insert(new_encoding.clone(), (encoding.clone(), step));
+        heap.push(State {
+          estimate: new_cost + new_board.min_cost_to_finish(),
+          cost: new_cost,
+          encoding: new_encoding,
+        });
+      }
+    }
+  }
+
+  None
+}
+
+// Accepts `3,7 1,4`, `(3,7) -> (1,4)` and the like
+fn parse_player_move(line: &str) -> Option<(Point, Point)> {
+  let points: Vec<Point> = line
+    .replace(['(', ')'], " ")
+    .split_whitespace()
+    .filter(|token|*token != "->")
+    .map(|token|{
+      let coordinates: Vec<i64> = token.split(",").filter_map(|x|x.trim().parse().ok()).collect();
+      if coordinates.len() == 2 { Some(Point::new(coordinates[0], coordinates[1])) } else { None }
+    })
+    .collect::<Option<Vec<Point>>>()?;
+  if points.len() != 2 {
+    return None;
+  }
+  return Some((points[0].clone(), points[1].clone()));
+}
+
+// Lets the player move amphipods by hand, then compares the energy spent with the optimal solution
+fn play(original_board: &Board) {
+  let mut board = original_board.clone();
+  let mut history: Vec<(Board, i64)> = Vec::new();
+  let mut cost = 0;
+  let stdin = io::stdin();
+
+  loop {
+    println!("{}", board);
+    println!("💸 Energy: {}", cost);
+    if board.is_solved() {
+      break;
+    }
+    println!("Move as `row,col row,col`, `undo` or `quit`:");
+    let mut line = String::new();
+    if stdin.lock().read_line(&mut line).unwrap() == 0 {
+      break;
+    }
+    match line.trim() {
+      "quit" => break,
+      "undo" => {
+        match history.pop() {
+          Some((previous, previous_cost)) => {
+            board = previous;
+            cost = previous_cost;
+          },
+          None => println!("Nothing to undo"),
         }
+        continue;
+      },
+      _ => {},
+    }
 
-        let ret = solve(original_board,
-          &new_board, 
-          new_cost, 
-          new_min, 
-          &new_steps, 
-          &new_solutions);
-        
-          new_solutions = ret.0;
-          new_min = ret.1;
-      }
+    let (from, to) = match parse_player_move(&line) {
+      Some(m) => m,
+      None => {
+        println!("Cannot read move `{}`", line.trim());
+        continue;
+      },
+    };
+    let letter = match board.amphipods.get(&from) {
+      Some(l) => *l,
+      None => {
+        println!("No amphipod at ({},{})", from.row, from.col);
+        continue;
+      },
+    };
+    let possible_moves = board.possible_moves(&letter, &from);
+    if !possible_moves.contains(&to) {
+      let destinations: Vec<String> = possible_moves.iter().map(|p|format!("({},{})", p.row, p.col)).collect();
+      println!("{} at ({},{}) cannot go to ({},{}), it can go to [{}]", letter, from.row, from.col, to.row, to.col, destinations.join(", "));
+      continue;
     }
-    return (new_solutions, new_min);
+
+    let step = Step { letter, from, to };
+    history.push((board.clone(), cost));
+    cost += step.cost(&board.burrow);
+    board = board.move_amphipod(&step.from, &step.to);
+  }
+
+  if !board.is_solved() {
+    println!("Gave up after spending {} energy", cost);
+    return;
+  }
+  let optimal = solve(original_board).unwrap();
+  if cost == optimal.cost {
+    println!("🏆 Solved with the optimal {} energy", cost);
+  } else {
+    println!("Solved with {} energy, optimal is {} ({} more)", cost, optimal.cost, cost - optimal.cost);
+  }
 }
 
-fn solution_1(data: &Vec<String>) -> i64 {
-  let board = Board::parse(&data);
-  let ret = solve(&board, 
-    &board, 
-    0, 
-    i64::MAX, 
-    &Vec::new(),
-  &Vec::new());
+fn solution_1(data: &Vec<String>) -> Result<i64, String> {
+  let board = Board::parse(&data)?;
+  let solution = solve(&board).ok_or("The burrow has no solution")?;
 
-    return ret.1;
+  return Ok(solution.cost);
 }
 
-fn solution_2(data: &Vec<String>) -> i64 {
+// Part 2 folds two rows into the rooms of the standard 4 rooms, 2 deep puzzle only
+fn unfold(data: &Vec<String>) -> Result<Vec<String>, String> {
+  let burrow = Burrow::parse(data)?;
+  let mut room_cols: Vec<i64> = burrow.room_col_by_letter.values().copied().collect();
+  room_cols.sort();
+  if room_cols != vec![3, 5, 7, 9] || burrow.room_depth != 2 {
+    return Err(format!("Unfolding needs the standard 4 rooms 2 deep burrow, got {} rooms {} deep", room_cols.len(), burrow.room_depth));
+  }
   let mut data = data.clone();
   data.insert(3, "  #D#C#B#A#".to_string());
   data.insert(4, "  #D#B#A#C#".to_string());
-  let board = Board::parse(&data);
-  let ret = solve(&board, 
-    &board, 
-    0, 
-    i64::MAX, 
-    &Vec::new(),
-  &Vec::new());
-
-    return ret.1;
+  return Ok(data);
+}
+
+fn solution_2(data: &Vec<String>) -> Result<i64, String> {
+  let board = Board::parse(&unfold(data)?)?;
+  let solution = solve(&board).ok_or("The unfolded burrow has no solution")?;
+
+  return Ok(solution.cost);
 }
 
 fn main() {
   let test = false;
+  let export = false;
+  let replay = false;
+  let interactive = false;
+  let frame_delay = Duration::from_millis(500);
   let mut file_path: String = "inputs/day23".to_string();
   let mut emoji: String = "🎉".to_string();
   if test {
@@ -483,5 +690,49 @@ fn main() {
               .map(|line|line.unwrap())
               .collect();
 
-  println!(r#"{:?} Part 2 test result is {:?}"#, emoji, solution_2(&data));
+  let board = match Board::parse(&data) {
+    Ok(board) => board,
+    Err(e) => {
+      println!("❌ Invalid burrow: {}", e);
+      return;
+    }
+  };
+
+  if interactive {
+    play(&board);
+    return;
+  }
+
+  match solution_1(&data) {
+    Ok(result) => println!(r#"{:?} Part 1 result is {:?}"#, emoji, result),
+    Err(e) => println!("❌ Part 1 failed: {}", e),
+  }
+
+  match solution_2(&data) {
+    Ok(result) => println!(r#"{:?} Part 2 test result is {:?}"#, emoji, result),
+    Err(e) => println!("Part 2 skipped: {}", e),
+  }
+
+  if export || replay {
+    // the unfolded burrow when there is one
+    let board = unfold(&data).and_then(|unfolded|Board::parse(&unfolded)).unwrap_or(board);
+    let solution = match solve(&board) {
+      Some(solution) => solution,
+      None => return,
+    };
+    if replay {
+      solution.replay(frame_delay);
+    }
+    if export {
+      let output_path = "outputs/day23.moves.txt";
+      fs::create_dir_all("outputs").unwrap();
+      fs::write(output_path, solution.to_text()).unwrap();
+      // load the moves back, as they would be when edited by hand
+      let text = fs::read_to_string(output_path).unwrap();
+      match validate_moves(&board, &text) {
+        Ok(cost) => println!("Moves in {} are valid, cost {}", output_path, cost),
+        Err(e) => println!("Moves in {} are invalid: {}", output_path, e),
+      }
+    }
+  }
 }
\ No newline at end of file